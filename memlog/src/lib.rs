@@ -1 +1,4 @@
 pub mod log;
+pub mod shrink;
+pub mod strategy;
+pub mod trace;
@@ -1,5 +1,7 @@
-use rand::{Rng, RngCore, SeedableRng};
+use crate::strategy::{LoadStrategy, Uniform};
+use rand::{Rng, SeedableRng};
 use rand_chacha::ChaCha8Rng;
+use sched::seed::time_seed;
 use std::collections::HashMap;
 use std::sync::atomic::Ordering;
 
@@ -70,6 +72,7 @@ pub struct MemorySystem {
     pub log: Vec<MemoryOperation>,
     pub acc: Vec<MemoryOperation>,
     pub threads: Vec<ThreadView>,
+    pub strategy: Box<dyn LoadStrategy>,
 }
 
 impl MemorySystem {
@@ -89,9 +92,9 @@ impl MemorySystem {
 
         let view = &mut self.threads[thread];
 
-        let all_ops = std::iter::once(&self.acc[addr]).chain(self.log.iter());
+        let mut all_ops = std::iter::once(&self.acc[addr]).chain(self.log.iter());
 
-        let choice: &MemoryOperation = all_ops.filter(|mo| mo.address == addr).last().unwrap();
+        let choice: &MemoryOperation = all_ops.rfind(|mo| mo.address == addr).unwrap();
 
         let (load_ordering, store_ordering) = if success == Ordering::AcqRel {
             (Ordering::Acquire, Ordering::Release)
@@ -202,8 +205,7 @@ impl MemorySystem {
         success: Ordering,
         failure: Ordering,
     ) -> Result<usize, usize> {
-        let s = std::time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as u64;
-        let mut rng = ChaCha8Rng::seed_from_u64(s);
+        let mut rng = ChaCha8Rng::seed_from_u64(time_seed());
 
         if rng.gen_bool(0.5) {
             self.op(
@@ -341,21 +343,18 @@ impl MemorySystem {
         assert!(
             level == Ordering::Relaxed || level == Ordering::Acquire || level == Ordering::SeqCst
         );
-        let s = std::time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as u64;
-        let mut rng = ChaCha8Rng::seed_from_u64(s);
-
         let view = &mut self.threads[thread];
 
         let all_ops = std::iter::once(&self.acc[addr]).chain(self.log.iter());
 
         let possible: Vec<&MemoryOperation> = all_ops.filter(|mo| mo.address == addr).collect();
 
-        let seq_cst_ops = possible.iter().filter(|mo| mo.level == Ordering::SeqCst);
+        let mut seq_cst_ops = possible.iter().filter(|mo| mo.level == Ordering::SeqCst);
 
         let minimum_op = if level == Ordering::SeqCst {
             // A seq_cst load will see the latest seq_cst store if it exists
             let latest_seq_cst_op = seq_cst_ops
-                .last()
+                .next_back()
                 .map(|mo| mo.global_sequence)
                 .unwrap_or(0_usize);
 
@@ -370,8 +369,7 @@ impl MemorySystem {
         } else {
             // A seq_cst fence on this thread causes the latest prior seq_cst store to be the minimum
             seq_cst_ops
-                .filter(|mo| mo.global_sequence < view.min_seq_cst_sequence)
-                .last()
+                .rfind(|mo| mo.global_sequence < view.min_seq_cst_sequence)
                 .map(|v| v.global_sequence)
                 .unwrap_or(0_usize)
         };
@@ -391,7 +389,7 @@ impl MemorySystem {
 
         let possible = &possible[first_ind..];
 
-        let choice = possible[(rng.next_u32() as usize) % possible.len()];
+        let choice = possible[self.strategy.choose(possible.len())];
 
         Self::read_synchronize(view, choice, level);
 
@@ -407,11 +405,19 @@ impl Default for MemorySystem {
             global_sequence: 10,
             seq_cst_sequence: Default::default(),
             log: vec![],
+            strategy: Box::new(Uniform::default()),
         }
     }
 }
 
 impl MemorySystem {
+    pub fn with_strategy<S: LoadStrategy + 'static>(strategy: S) -> Self {
+        MemorySystem {
+            strategy: Box::new(strategy),
+            ..Default::default()
+        }
+    }
+
     pub fn add_thread(&mut self) -> usize {
         let v = self.threads.len();
 
@@ -0,0 +1,263 @@
+use crate::strategy::LoadStrategy;
+use crate::trace::{Decision, Trace};
+use sched::scheduler::Scheduler;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+
+impl Trace {
+    // Scheduling decisions that switch away from a thread that could have kept running
+    pub fn preemptions(&self) -> usize {
+        let mut last = None;
+        let mut count = 0;
+
+        for d in self.decisions.iter() {
+            if let Decision::Schedule { runnable, choice } = d {
+                let thread = runnable[*choice];
+
+                if last.is_some_and(|l| l != thread && runnable.contains(&l)) {
+                    count += 1;
+                }
+
+                last = Some(thread);
+            }
+        }
+
+        count
+    }
+
+    // Loads that read something other than the latest visible store
+    pub fn stale_loads(&self) -> usize {
+        self.decisions
+            .iter()
+            .filter(|d| matches!(d, Decision::Load { options, choice } if choice + 1 != *options))
+            .count()
+    }
+
+    fn cost(&self) -> usize {
+        self.preemptions() + self.stale_loads()
+    }
+}
+
+// A trace restated relative to each thread, so editing one part doesn't misalign the rest:
+// the sequence of threads scheduled, and how far behind the latest store each thread's nth load read
+#[derive(Clone, Default)]
+struct Plan {
+    schedule: Vec<usize>,
+    staleness: HashMap<(usize, usize), usize>,
+}
+
+impl Plan {
+    fn from_trace(trace: &Trace) -> Plan {
+        let mut plan = Plan::default();
+        let mut loads: HashMap<usize, usize> = HashMap::new();
+
+        for d in trace.decisions.iter() {
+            match d {
+                Decision::Schedule { runnable, choice } => plan.schedule.push(runnable[*choice]),
+                Decision::Load { options, choice } => {
+                    // Loads happen during the step of the thread scheduled last
+                    let thread = plan.schedule.last().copied().unwrap_or(0);
+                    let nth = loads.entry(thread).or_default();
+
+                    if choice + 1 != *options {
+                        plan.staleness.insert((thread, *nth), options - 1 - choice);
+                    }
+
+                    *nth += 1;
+                }
+            }
+        }
+
+        plan
+    }
+
+    // Variants with a single preemption or stale load removed
+    fn simplifications(&self) -> Vec<Plan> {
+        let mut res = vec![];
+
+        let mut stale: Vec<_> = self.staleness.keys().copied().collect();
+        stale.sort();
+
+        for key in stale {
+            let mut simpler = self.clone();
+            simpler.staleness.remove(&key);
+            res.push(simpler);
+        }
+
+        let s = &self.schedule;
+
+        for p in 1..s.len() {
+            let (from, to) = (s[p - 1], s[p]);
+
+            if from == to {
+                continue;
+            }
+
+            // The next block of the preempted thread, if it runs again
+            let next = match (p..s.len()).find(|&i| s[i] == from) {
+                Some(next) => next,
+                None => continue,
+            };
+            let end = (next..s.len()).find(|&i| s[i] != from).unwrap_or(s.len());
+
+            // Either the preempted thread carries on with its next block straight away...
+            let mut carry_on = s[..p].to_vec();
+            carry_on.extend_from_slice(&s[next..end]);
+            carry_on.extend_from_slice(&s[p..next]);
+            carry_on.extend_from_slice(&s[end..]);
+
+            // ...or it's held back until its next block, joining the two
+            let start = (0..p).rev().find(|&i| s[i] != from).map_or(0, |i| i + 1);
+            let mut hold_back = s[..start].to_vec();
+            hold_back.extend_from_slice(&s[p..next]);
+            hold_back.extend_from_slice(&s[start..p]);
+            hold_back.extend_from_slice(&s[next..]);
+
+            for schedule in [carry_on, hold_back] {
+                res.push(Plan {
+                    schedule,
+                    staleness: self.staleness.clone(),
+                });
+            }
+        }
+
+        res
+    }
+}
+
+struct GuideState {
+    schedule: VecDeque<usize>,
+    staleness: HashMap<(usize, usize), usize>,
+    loads: HashMap<usize, usize>,
+    last: Option<usize>,
+    taken: Trace,
+}
+
+/*
+A lenient replay. Threads are scheduled in the order the guide gives where they're still runnable,
+and loads read as far behind the latest store as they did before. Where the guide no longer fits
+the run falls back to the least surprising choice: continuing the last thread, and reading the
+latest store. The decisions actually taken are recorded, and form a strict trace.
+*/
+#[derive(Clone)]
+pub struct Guide {
+    state: Arc<Mutex<GuideState>>,
+}
+
+pub struct GuideScheduler {
+    state: Arc<Mutex<GuideState>>,
+}
+
+pub struct GuideStrategy {
+    state: Arc<Mutex<GuideState>>,
+}
+
+impl Guide {
+    pub fn new(trace: &Trace) -> Self {
+        Self::from_plan(Plan::from_trace(trace))
+    }
+
+    fn from_plan(plan: Plan) -> Self {
+        Guide {
+            state: Arc::new(Mutex::new(GuideState {
+                schedule: plan.schedule.into(),
+                staleness: plan.staleness,
+                loads: HashMap::new(),
+                last: None,
+                taken: Trace::default(),
+            })),
+        }
+    }
+
+    pub fn scheduler(&self) -> GuideScheduler {
+        GuideScheduler {
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn strategy(&self) -> GuideStrategy {
+        GuideStrategy {
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn trace(&self) -> Trace {
+        self.state.lock().unwrap().taken.clone()
+    }
+}
+
+impl Scheduler for GuideScheduler {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        let mut state = self.state.lock().unwrap();
+
+        let mut choice = None;
+
+        // Threads that can't run any more are dropped from the guide
+        while let Some(thread) = state.schedule.pop_front() {
+            choice = runnable.iter().position(|&t| t == thread);
+
+            if choice.is_some() {
+                break;
+            }
+        }
+
+        let last = state.last;
+        let choice = choice
+            .or_else(|| runnable.iter().position(|&t| Some(t) == last))
+            .unwrap_or(0);
+
+        state.last = Some(runnable[choice]);
+        state.taken.decisions.push(Decision::Schedule {
+            runnable: runnable.to_vec(),
+            choice,
+        });
+
+        choice
+    }
+}
+
+impl LoadStrategy for GuideStrategy {
+    fn choose(&mut self, options: usize) -> usize {
+        let mut state = self.state.lock().unwrap();
+
+        let thread = state.last.unwrap_or(0);
+        let nth = *state.loads.get(&thread).unwrap_or(&0);
+        state.loads.insert(thread, nth + 1);
+
+        let staleness = *state.staleness.get(&(thread, nth)).unwrap_or(&0);
+        let choice = options - 1 - staleness.min(options - 1);
+
+        state
+            .taken
+            .decisions
+            .push(Decision::Load { options, choice });
+
+        choice
+    }
+}
+
+/*
+Minimises a failing trace. `fails` runs the program under the given guide and returns whether the
+failure reproduced. Preemptions and stale loads are removed one at a time for as long as the
+program keeps failing, and the smallest failing trace found is returned.
+*/
+pub fn shrink<F: FnMut(&Guide) -> bool>(trace: &Trace, mut fails: F) -> Trace {
+    let mut best = trace.clone();
+
+    'outer: loop {
+        for plan in Plan::from_trace(&best).simplifications() {
+            let guide = Guide::from_plan(plan);
+
+            if fails(&guide) {
+                let taken = guide.trace();
+
+                if taken.cost() < best.cost() {
+                    best = taken;
+                    continue 'outer;
+                }
+            }
+        }
+
+        return best;
+    }
+}
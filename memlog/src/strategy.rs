@@ -0,0 +1,148 @@
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use sched::explore::DecisionTree;
+use sched::seed::time_seed;
+use std::sync::{Arc, Mutex};
+
+// Decides which of the stores visible to a load is observed.
+// Options are ordered from oldest to newest, so `options - 1` is the latest store.
+pub trait LoadStrategy: Send {
+    fn choose(&mut self, options: usize) -> usize;
+}
+
+impl<L: LoadStrategy + ?Sized> LoadStrategy for Box<L> {
+    fn choose(&mut self, options: usize) -> usize {
+        (**self).choose(options)
+    }
+}
+
+// Picks any visible store with equal probability
+pub struct Uniform {
+    rng: ChaCha8Rng,
+}
+
+impl Uniform {
+    pub fn new(seed: u64) -> Self {
+        Uniform {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for Uniform {
+    fn default() -> Self {
+        Self::new(time_seed())
+    }
+}
+
+impl LoadStrategy for Uniform {
+    fn choose(&mut self, options: usize) -> usize {
+        (self.rng.next_u32() as usize) % options
+    }
+}
+
+// Starts at the newest store and steps back one store at a time with probability 1 - bias,
+// modelling hardware where reading the latest value is the common case
+pub struct BiasLatest {
+    rng: ChaCha8Rng,
+    bias: f64,
+}
+
+impl BiasLatest {
+    pub fn new(bias: f64, seed: u64) -> Self {
+        assert!((0.0..=1.0).contains(&bias));
+
+        BiasLatest {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            bias,
+        }
+    }
+
+    pub fn with_bias(bias: f64) -> Self {
+        Self::new(bias, time_seed())
+    }
+}
+
+impl LoadStrategy for BiasLatest {
+    fn choose(&mut self, options: usize) -> usize {
+        let mut choice = options - 1;
+
+        while choice > 0 && !self.rng.gen_bool(self.bias) {
+            choice -= 1;
+        }
+
+        choice
+    }
+}
+
+// The mirror image of BiasLatest, favouring the oldest store the load is allowed to see.
+// This is the most adversarial choice for most algorithms.
+pub struct BiasOldest {
+    rng: ChaCha8Rng,
+    bias: f64,
+}
+
+impl BiasOldest {
+    pub fn new(bias: f64, seed: u64) -> Self {
+        assert!((0.0..=1.0).contains(&bias));
+
+        BiasOldest {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+            bias,
+        }
+    }
+
+    pub fn with_bias(bias: f64) -> Self {
+        Self::new(bias, time_seed())
+    }
+}
+
+impl LoadStrategy for BiasOldest {
+    fn choose(&mut self, options: usize) -> usize {
+        let mut choice = 0;
+
+        while choice < options - 1 && !self.rng.gen_bool(self.bias) {
+            choice += 1;
+        }
+
+        choice
+    }
+}
+
+// Enumerates every combination of load choices across repeated runs, depth first.
+// Each run takes a fresh strategy from `strategy()`, and `advance()` moves to the next
+// combination, returning false once every combination has been visited.
+//
+// Coverage is only complete if the schedule is the same on every run (eg run_sequential).
+#[derive(Default, Clone)]
+pub struct Exhaustive {
+    // Decisions are told apart by how many stores the load could see
+    tree: Arc<Mutex<DecisionTree<usize>>>,
+}
+
+pub struct ExhaustiveStrategy {
+    tree: Arc<Mutex<DecisionTree<usize>>>,
+}
+
+impl Exhaustive {
+    pub fn strategy(&self) -> ExhaustiveStrategy {
+        self.tree.lock().unwrap().restart();
+
+        ExhaustiveStrategy {
+            tree: self.tree.clone(),
+        }
+    }
+
+    pub fn advance(&self) -> bool {
+        self.tree.lock().unwrap().advance()
+    }
+}
+
+impl LoadStrategy for ExhaustiveStrategy {
+    fn choose(&mut self, options: usize) -> usize {
+        self.tree
+            .lock()
+            .unwrap()
+            .choose(options, || (0..options).collect())
+    }
+}
@@ -0,0 +1,300 @@
+use crate::strategy::LoadStrategy;
+use sched::scheduler::Scheduler;
+use std::fmt::{Display, Formatter};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+
+// A single choice made during a run. Scheduling decisions keep the runnable threads they were
+// chosen from, and load decisions the number of visible stores, so replays can detect divergence.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Decision {
+    Schedule { runnable: Vec<usize>, choice: usize },
+    Load { options: usize, choice: usize },
+}
+
+impl Display for Decision {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Decision::Schedule { runnable, choice } => {
+                let runnable: Vec<String> = runnable.iter().map(|t| t.to_string()).collect();
+                write!(f, "schedule {} {}", choice, runnable.join(","))
+            }
+            Decision::Load { options, choice } => write!(f, "load {} {}", choice, options),
+        }
+    }
+}
+
+impl Decision {
+    fn parse(line: &str) -> Result<Decision, String> {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+
+        let number = |s: &str| {
+            s.parse::<usize>()
+                .map_err(|_| format!("Invalid number '{}' in '{}'", s, line))
+        };
+
+        match parts.as_slice() {
+            ["schedule", choice, runnable] => Ok(Decision::Schedule {
+                choice: number(choice)?,
+                runnable: runnable.split(',').map(number).collect::<Result<_, _>>()?,
+            }),
+            ["load", choice, options] => Ok(Decision::Load {
+                choice: number(choice)?,
+                options: number(options)?,
+            }),
+            _ => Err(format!("Unrecognised decision '{}'", line)),
+        }
+    }
+}
+
+// The exact sequence of decisions made during a run, one per line when saved.
+// Unlike a seed, a trace still describes the same execution after unrelated code changes.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub decisions: Vec<Decision>,
+}
+
+impl Display for Trace {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "# temper trace")?;
+
+        for d in self.decisions.iter() {
+            writeln!(f, "{}", d)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Trace {
+    pub fn parse(text: &str) -> Result<Trace, String> {
+        let decisions = text
+            .lines()
+            .map(|l| l.trim())
+            .filter(|l| !l.is_empty() && !l.starts_with('#'))
+            .map(Decision::parse)
+            .collect::<Result<_, _>>()?;
+
+        Ok(Trace { decisions })
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> std::io::Result<()> {
+        std::fs::write(path, self.to_string())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> std::io::Result<Trace> {
+        let text = std::fs::read_to_string(path)?;
+
+        Trace::parse(&text).map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+// Records the decisions of a scheduler and load strategy into a single shared trace
+#[derive(Clone, Default)]
+pub struct Recording {
+    trace: Arc<Mutex<Trace>>,
+}
+
+pub struct RecordScheduler<S: Scheduler> {
+    inner: S,
+    trace: Arc<Mutex<Trace>>,
+}
+
+pub struct RecordStrategy<L: LoadStrategy> {
+    inner: L,
+    trace: Arc<Mutex<Trace>>,
+}
+
+impl Recording {
+    pub fn scheduler<S: Scheduler>(&self, inner: S) -> RecordScheduler<S> {
+        RecordScheduler {
+            inner,
+            trace: self.trace.clone(),
+        }
+    }
+
+    pub fn strategy<L: LoadStrategy>(&self, inner: L) -> RecordStrategy<L> {
+        RecordStrategy {
+            inner,
+            trace: self.trace.clone(),
+        }
+    }
+
+    pub fn trace(&self) -> Trace {
+        self.trace.lock().unwrap().clone()
+    }
+}
+
+impl<S: Scheduler> Scheduler for RecordScheduler<S> {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        let choice = self.inner.choose(runnable);
+
+        self.trace
+            .lock()
+            .unwrap()
+            .decisions
+            .push(Decision::Schedule {
+                runnable: runnable.to_vec(),
+                choice,
+            });
+
+        choice
+    }
+}
+
+impl<L: LoadStrategy> LoadStrategy for RecordStrategy<L> {
+    fn choose(&mut self, options: usize) -> usize {
+        let choice = self.inner.choose(options);
+
+        self.trace
+            .lock()
+            .unwrap()
+            .decisions
+            .push(Decision::Load { options, choice });
+
+        choice
+    }
+}
+
+struct ReplayState {
+    trace: Trace,
+    position: usize,
+    diverged: Option<String>,
+}
+
+impl ReplayState {
+    // Divergence is reported after the lock is released, so the state isn't poisoned for
+    // threads that make decisions afterwards; they report the same divergence
+    fn next(state: &Mutex<ReplayState>, offered: Decision) -> usize {
+        let res = state.lock().unwrap().try_next(offered);
+        res.unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    fn try_next(&mut self, offered: Decision) -> Result<usize, String> {
+        if let Some(e) = &self.diverged {
+            return Err(e.clone());
+        }
+
+        let res = self.check(offered);
+        self.diverged = res.as_ref().err().cloned();
+        res
+    }
+
+    fn check(&mut self, offered: Decision) -> Result<usize, String> {
+        let position = self.position;
+        self.position += 1;
+
+        let expected = self.trace.decisions.get(position).ok_or_else(|| {
+            format!(
+                "Replay diverged at decision {}: the trace ended, but the program made {}",
+                position,
+                describe(&offered)
+            )
+        })?;
+
+        match (expected, &offered) {
+            (
+                Decision::Schedule { runnable, choice },
+                Decision::Schedule {
+                    runnable: offered, ..
+                },
+            ) if runnable == offered => Ok(*choice),
+            (
+                Decision::Load { options, choice },
+                Decision::Load {
+                    options: offered, ..
+                },
+            ) if options == offered => Ok(*choice),
+            _ => Err(format!(
+                "Replay diverged at decision {}: the trace has {}, but the program made {}",
+                position,
+                describe(expected),
+                describe(&offered)
+            )),
+        }
+    }
+}
+
+fn describe(decision: &Decision) -> String {
+    match decision {
+        Decision::Schedule { runnable, .. } => {
+            format!("a scheduling decision between threads {:?}", runnable)
+        }
+        Decision::Load { options, .. } => format!("a load with {} visible stores", options),
+    }
+}
+
+// Drives a run with the decisions of a recorded trace, panicking as soon as the program
+// offers a choice that doesn't match the one recorded
+#[derive(Clone)]
+pub struct Replay {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+pub struct ReplayScheduler {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+pub struct ReplayStrategy {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl Replay {
+    pub fn new(trace: Trace) -> Self {
+        Replay {
+            state: Arc::new(Mutex::new(ReplayState {
+                trace,
+                position: 0,
+                diverged: None,
+            })),
+        }
+    }
+
+    pub fn scheduler(&self) -> ReplayScheduler {
+        ReplayScheduler {
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn strategy(&self) -> ReplayStrategy {
+        ReplayStrategy {
+            state: self.state.clone(),
+        }
+    }
+
+    // Panics if the program finished without consuming the whole trace
+    pub fn finish(&self) {
+        let state = self.state.lock().unwrap();
+
+        if let Some(e) = &state.diverged {
+            panic!("{}", e);
+        }
+
+        let remaining = state.trace.decisions.len() - state.position;
+
+        if remaining > 0 {
+            panic!(
+                "Replay diverged at decision {}: the program finished with {} decisions left in the trace",
+                state.position, remaining
+            );
+        }
+    }
+}
+
+impl Scheduler for ReplayScheduler {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        ReplayState::next(
+            &self.state,
+            Decision::Schedule {
+                runnable: runnable.to_vec(),
+                choice: 0,
+            },
+        )
+    }
+}
+
+impl LoadStrategy for ReplayStrategy {
+    fn choose(&mut self, options: usize) -> usize {
+        ReplayState::next(&self.state, Decision::Load { options, choice: 0 })
+    }
+}
@@ -1,41 +1,90 @@
 use memlog::log::MemorySystem;
-use rand::{RngCore, SeedableRng};
-use rand_chacha::ChaCha8Rng;
+use memlog::shrink::Guide;
+use memlog::strategy::{LoadStrategy, Uniform};
+use memlog::trace::{Recording, Replay, Trace};
+use sched::coroutine::{self, Task, Tasks};
+use sched::scheduler::{Scheduler, UniformScheduler};
+use std::collections::HashMap;
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::sync::atomic::Ordering;
-use std::sync::{Arc, Barrier, Mutex};
+use std::sync::{Arc, Condvar, Mutex};
 use std::thread;
 use std::thread::JoinHandle;
 
+#[derive(Copy, Clone, Default)]
 pub struct ThreadState {
     pub finished: bool,
     pub waiting: bool,
-    pub barrier: Arc<Barrier>,
 }
 
-impl ThreadState {
-    pub fn wait(thread_state: &Arc<Mutex<ThreadState>>) {
-        {
-            let mut ts = thread_state.lock().unwrap();
-            ts.waiting = true;
-            let barrier = ts.barrier.clone();
-            drop(ts);
-            barrier.wait();
+// Every thread's state behind one lock, so threads and the driver sleep until it's their turn
+#[derive(Default)]
+pub struct Control {
+    states: Mutex<HashMap<usize, ThreadState>>,
+    changed: Condvar,
+}
+
+impl Control {
+    fn update<F: FnOnce(&mut ThreadState)>(&self, thread: usize, f: F) {
+        f(self.states.lock().unwrap().entry(thread).or_default());
+        self.changed.notify_all();
+    }
+
+    // Blocks until the driver picks this thread to take its next step
+    pub fn wait(&self, thread: usize) {
+        if coroutine::active() {
+            return coroutine::suspend();
         }
 
-        while thread_state.lock().unwrap().waiting {}
+        self.update(thread, |s| s.waiting = true);
+
+        let states = self.states.lock().unwrap();
+        drop(
+            self.changed
+                .wait_while(states, |s| s[&thread].waiting)
+                .unwrap(),
+        );
+    }
+
+    fn finish(&self, thread: usize) {
+        self.update(thread, |s| s.finished = true);
+    }
+
+    fn resume(&self, thread: usize) {
+        self.update(thread, |s| s.waiting = false);
+    }
+
+    // Blocks until every thread is waiting or finished, and returns those still running
+    fn runnable(&self, threads: &[usize]) -> Vec<usize> {
+        let states = self.states.lock().unwrap();
+        let states = self
+            .changed
+            .wait_while(states, |s| {
+                threads.iter().any(|t| {
+                    let ts = s.get(t).copied().unwrap_or_default();
+                    !ts.finished && !ts.waiting
+                })
+            })
+            .unwrap();
+
+        threads
+            .iter()
+            .copied()
+            .filter(|t| !states[t].finished)
+            .collect()
     }
 }
 
 pub struct Value {
     pub thread: usize,
     pub addr: usize,
-    pub thread_state: Arc<Mutex<ThreadState>>,
+    pub control: Arc<Control>,
     pub memory: Arc<Mutex<MemorySystem>>,
 }
 
 impl Value {
     pub fn wait(&mut self) {
-        ThreadState::wait(&self.thread_state);
+        self.control.wait(self.thread);
     }
 
     #[allow(unused)]
@@ -101,7 +150,7 @@ impl Value {
 
 #[allow(unused)]
 pub struct Environment {
-    pub thread_state: Arc<Mutex<ThreadState>>,
+    pub control: Arc<Control>,
     pub a: Value,
     pub b: Value,
     pub c: Value,
@@ -112,20 +161,28 @@ pub struct Environment {
 impl Environment {
     #[allow(unused)]
     pub fn fence(&mut self, ordering: Ordering) {
-        ThreadState::wait(&self.thread_state);
+        self.control.wait(self.a.thread);
         let mut mem = self.a.memory.lock().unwrap();
         mem.fence(self.a.thread, ordering)
     }
 }
 
-pub struct Thread<T> {
-    pub thread_state: Arc<Mutex<ThreadState>>,
-    pub handle: JoinHandle<T>,
+pub enum Handle<T: 'static> {
+    Os(JoinHandle<T>),
+    Task(Task<thread::Result<T>>),
+}
+
+pub struct Thread<T: 'static> {
+    pub id: usize,
+    pub handle: Handle<T>,
 }
 
 #[derive(Default)]
 pub struct LogTest<T: Copy + Send + 'static> {
     pub fns: Vec<Box<dyn FnMut(Environment) -> T + Send>>,
+    pub strategy: Option<Box<dyn LoadStrategy>>,
+    pub scheduler: Option<Box<dyn Scheduler>>,
+    pub coroutines: bool,
 }
 
 impl<T: Copy + Send + 'static> LogTest<T> {
@@ -133,18 +190,82 @@ impl<T: Copy + Send + 'static> LogTest<T> {
         self.fns.push(Box::new(f))
     }
 
+    // Overrides the default uniform choice of which visible store each load reads
+    #[allow(unused)]
+    pub fn set_strategy<S: LoadStrategy + 'static>(&mut self, strategy: S) {
+        self.strategy = Some(Box::new(strategy));
+    }
+
+    // Overrides the default uniform choice of which thread runs at each step
+    #[allow(unused)]
+    pub fn set_scheduler<S: Scheduler + 'static>(&mut self, scheduler: S) {
+        self.scheduler = Some(Box::new(scheduler));
+    }
+
+    // Makes each thread a task, resumed on this thread whenever the scheduler picks it
+    #[allow(unused)]
+    pub fn use_coroutines(&mut self) {
+        self.coroutines = true;
+    }
+
+    // Records every scheduling decision and load choice made by the next run
+    #[allow(unused)]
+    pub fn record(&mut self) -> Recording {
+        let recording = Recording::default();
+
+        let scheduler = self
+            .scheduler
+            .take()
+            .unwrap_or_else(|| Box::new(UniformScheduler::default()));
+        let strategy = self
+            .strategy
+            .take()
+            .unwrap_or_else(|| Box::new(Uniform::default()));
+
+        self.scheduler = Some(Box::new(recording.scheduler(scheduler)));
+        self.strategy = Some(Box::new(recording.strategy(strategy)));
+
+        recording
+    }
+
+    // Drives the next run with the decisions from a recorded trace
+    #[allow(unused)]
+    pub fn replay(&mut self, trace: Trace) -> Replay {
+        let replay = Replay::new(trace);
+
+        self.scheduler = Some(Box::new(replay.scheduler()));
+        self.strategy = Some(Box::new(replay.strategy()));
+
+        replay
+    }
+
+    // Drives the next run leniently from a guide, as used when shrinking traces
+    #[allow(unused)]
+    pub fn guide(&mut self, guide: &Guide) {
+        self.scheduler = Some(Box::new(guide.scheduler()));
+        self.strategy = Some(Box::new(guide.strategy()));
+    }
+
+    fn memory_system(&mut self) -> Arc<Mutex<MemorySystem>> {
+        let mut ms = MemorySystem::default();
+
+        if let Some(strategy) = self.strategy.take() {
+            ms.strategy = strategy;
+        }
+
+        ms.malloc(5);
+        Arc::new(Mutex::new(ms))
+    }
+
     pub fn spawn_thread<F: FnMut(Environment) -> T + Send + 'static + Sized>(
         ms: Arc<Mutex<MemorySystem>>,
+        control: Arc<Control>,
+        coroutines: bool,
         i: usize,
         mut f: F,
     ) -> Thread<T> {
         ms.lock().unwrap().add_thread();
-
-        let ts = Arc::new(Mutex::new(ThreadState {
-            finished: false,
-            waiting: false,
-            barrier: Arc::new(Barrier::new(2)),
-        }));
+        control.update(i, |_| {});
 
         let mut addr = 0;
 
@@ -152,7 +273,7 @@ impl<T: Copy + Send + 'static> LogTest<T> {
             let res = Value {
                 thread: i,
                 addr,
-                thread_state: ts.clone(),
+                control: control.clone(),
                 memory: ms.clone(),
             };
 
@@ -161,7 +282,7 @@ impl<T: Copy + Send + 'static> LogTest<T> {
         };
 
         let env = Environment {
-            thread_state: ts.clone(),
+            control: control.clone(),
             a: build_value(),
             b: build_value(),
             c: build_value(),
@@ -169,53 +290,69 @@ impl<T: Copy + Send + 'static> LogTest<T> {
             e: build_value(),
         };
 
+        if coroutines {
+            return Thread {
+                id: i,
+                handle: Handle::Task(Task::new(move || catch_unwind(AssertUnwindSafe(|| f(env))))),
+            };
+        }
+
         Thread {
-            thread_state: ts.clone(),
-            handle: thread::spawn(move || {
-                let res: T = f(env);
-                ts.lock().unwrap().finished = true;
-                res
-            }),
+            id: i,
+            handle: Handle::Os(thread::spawn(move || {
+                // A panicking thread still has to be marked finished, or drive never returns
+                let res = catch_unwind(AssertUnwindSafe(|| f(env)));
+                control.finish(i);
+
+                match res {
+                    Ok(res) => res,
+                    Err(e) => resume_unwind(e),
+                }
+            })),
         }
     }
 
-    pub fn drive(mut threads: Vec<Thread<T>>) -> Vec<T> {
-        let s = std::time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as u64;
-        let mut rng = ChaCha8Rng::seed_from_u64(s);
+    pub fn drive(
+        threads: Vec<Thread<T>>,
+        control: &Control,
+        scheduler: &mut dyn Scheduler,
+    ) -> Vec<T> {
+        let ids: Vec<usize> = threads.iter().map(|t| t.id).collect();
+        let mut handles = vec![];
+        let mut tasks = Tasks::default();
+
+        for t in threads {
+            match t.handle {
+                Handle::Os(h) => handles.push(h),
+                Handle::Task(task) => tasks.push(task),
+            }
+        }
+
+        if !tasks.is_empty() {
+            return tasks
+                .run(&ids, scheduler)
+                .into_iter()
+                .map(|r| r.unwrap_or_else(|e| resume_unwind(e)))
+                .collect();
+        }
 
         loop {
-            let mut all_finished = true;
-            let mut all_waiting = true;
-            for tsm in threads.iter() {
-                let ts = tsm.thread_state.lock().unwrap();
-                if !ts.finished {
-                    all_finished = false;
-
-                    if !ts.waiting {
-                        all_waiting = false;
-                    }
-                }
-            }
+            let runnable = control.runnable(&ids);
 
-            if all_finished {
+            if runnable.is_empty() {
                 break;
             }
 
-            if all_waiting {
-                let ind = (rng.next_u32() as usize) % threads.len();
-                let r = &mut threads[ind];
-                let mut l = r.thread_state.lock().unwrap();
-                if l.waiting {
-                    l.waiting = false;
-                    l.barrier.wait();
-                }
-            }
+            control.resume(runnable[scheduler.choose(&runnable)]);
         }
 
         let mut res = vec![];
 
-        for h in threads.drain(..) {
-            res.push(h.handle.join().unwrap())
+        for h in handles {
+            match h.join() {
+                Ok(v) => res.push(v),
+                Err(e) => resume_unwind(e),
+            }
         }
 
         res
@@ -224,28 +361,51 @@ impl<T: Copy + Send + 'static> LogTest<T> {
     // Runs all threads randomly interleaved
     #[allow(unused)]
     pub fn run(&mut self) -> Vec<T> {
-        let ms = Arc::new(Mutex::new(MemorySystem::default()));
-        ms.lock().unwrap().malloc(5);
+        let ms = self.memory_system();
 
+        let control = Arc::new(Control::default());
         let mut threads = vec![];
 
         for (i, f) in self.fns.drain(..).enumerate() {
-            threads.push(Self::spawn_thread(ms.clone(), i, f));
+            threads.push(Self::spawn_thread(
+                ms.clone(),
+                control.clone(),
+                self.coroutines,
+                i,
+                f,
+            ));
         }
 
-        Self::drive(threads)
+        let mut scheduler = self
+            .scheduler
+            .take()
+            .unwrap_or_else(|| Box::new(UniformScheduler::default()));
+
+        Self::drive(threads, &control, scheduler.as_mut())
     }
 
     // Runs Thread A fully, then Thread B, etc
     #[allow(unused)]
     pub fn run_sequential(&mut self) -> Vec<T> {
-        let ms = Arc::new(Mutex::new(MemorySystem::default()));
-        ms.lock().unwrap().malloc(5);
+        let ms = self.memory_system();
 
+        let control = Arc::new(Control::default());
         let mut results = vec![];
 
         for (i, f) in self.fns.drain(..).enumerate() {
-            results.push(Self::drive(vec![Self::spawn_thread(ms.clone(), i, f)])[0]);
+            results.push(
+                Self::drive(
+                    vec![Self::spawn_thread(
+                        ms.clone(),
+                        control.clone(),
+                        self.coroutines,
+                        i,
+                        f,
+                    )],
+                    &control,
+                    &mut UniformScheduler::default(),
+                )[0],
+            );
         }
 
         results
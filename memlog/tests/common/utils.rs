@@ -1,3 +1,4 @@
+use sched::scheduler::{BoundedScheduler, PreemptionBounded};
 use std::collections::HashSet;
 use std::fmt::Debug;
 use std::hash::Hash;
@@ -68,6 +69,27 @@ pub fn run_until_pred<
     false
 }
 
+// Runs every schedule with at most `bound` preemptions, collecting the outcomes. The explorer is
+// returned so callers can check whether the bound cut any schedules off.
+#[allow(unused)]
+pub fn explore_bounded<T: Eq + Hash, F: FnMut(BoundedScheduler) -> T>(
+    bound: usize,
+    mut f: F,
+) -> (HashSet<T>, PreemptionBounded) {
+    let explorer = PreemptionBounded::new(bound);
+    let mut res = HashSet::new();
+
+    loop {
+        res.insert(f(explorer.scheduler()));
+
+        if !explorer.advance() {
+            break;
+        }
+    }
+
+    (res, explorer)
+}
+
 pub fn permutations(possible: Vec<Vec<usize>>) -> Vec<Vec<usize>> {
     let mut out = vec![vec![]];
 
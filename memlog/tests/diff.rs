@@ -0,0 +1,111 @@
+use crate::common::harness::{Environment, LogTest};
+use memlog::strategy::Uniform;
+use sched::scheduler::UniformScheduler;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+use temper::temper::system::diff::{Column, ModelDiff};
+
+mod common;
+
+/* Message passing
+
+Thread 1:
+a = 1
+b = 1 (release in C11 if ordered)
+
+Thread 2:
+print(b) (acquire in C11 if ordered)
+print(a)
+*/
+
+fn c11(seed: u64, ordered: bool) -> Vec<usize> {
+    let (store, load) = if ordered {
+        (Ordering::Release, Ordering::Acquire)
+    } else {
+        (Ordering::Relaxed, Ordering::Relaxed)
+    };
+
+    let mut lt = LogTest::default();
+    lt.set_scheduler(UniformScheduler::new(seed));
+    // Generators seeded alike would make the same choices, and the loads would follow the schedule
+    lt.set_strategy(Uniform::new(!seed));
+
+    lt.add(move |mut eg: Environment| {
+        eg.a.store(1, Ordering::Relaxed);
+        eg.b.store(1, store);
+        0
+    });
+
+    lt.add(move |mut eg: Environment| {
+        let b = eg.b.load(load);
+        b * 10 + eg.a.load(Ordering::Relaxed)
+    });
+
+    let r = lt.run()[1];
+    vec![r / 10, r % 10]
+}
+
+fn temper(s: System) -> Vec<usize> {
+    let (a, b) = (Arc::new(Atomic::new(0)), Arc::new(Atomic::new(0)));
+    let result = Arc::new(Mutex::new(vec![]));
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new({
+            let (a, b) = (a.clone(), b.clone());
+            move || {
+                a.set(1);
+                b.set(1);
+            }
+        }),
+        Box::new({
+            let result = result.clone();
+            move || {
+                let b = *b.get();
+                *result.lock().unwrap() = vec![b, *a.get()];
+            }
+        }),
+    ];
+
+    s.run(fns).unwrap();
+
+    let r = result.lock().unwrap().clone();
+    r
+}
+
+#[test]
+fn test_c11_against_temper() {
+    let diff = ModelDiff::new(
+        vec![
+            Column::new("C11 relaxed", |seed| c11(seed, false)),
+            Column::new("C11 acq/rel", |seed| c11(seed, true)),
+            Column::model(MemoryModel::Intel, &temper),
+            Column::model(MemoryModel::ARM, &temper),
+        ],
+        0..300,
+        2,
+    );
+
+    // Relaxed C11 and ARM can both see the flag before the data, Intel and release/acquire can't
+    let stale = vec![1, 0];
+    assert_eq!(
+        diff.differences().into_iter().collect::<Vec<_>>(),
+        vec![&stale]
+    );
+
+    for (name, seen) in [
+        ("C11 relaxed", true),
+        ("C11 acq/rel", false),
+        ("Intel", false),
+        ("ARM", true),
+    ] {
+        let outcomes = diff.outcomes(name).unwrap();
+        assert_eq!(outcomes.outcomes.contains_key(&stale), seen, "{}", name);
+        assert_eq!(outcomes.runs(), 300);
+    }
+
+    assert!(diff
+        .to_string()
+        .starts_with("outcome  C11 relaxed  C11 acq/rel  Intel  ARM\n"));
+}
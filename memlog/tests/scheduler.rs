@@ -0,0 +1,67 @@
+use crate::common::harness::{Environment, LogTest};
+use crate::common::utils::explore_bounded;
+use memlog::strategy::BiasLatest;
+use sched::scheduler::{PctScheduler, Scheduler};
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+
+mod common;
+
+// A non atomic increment: both threads reading zero needs one preemption between load and store
+fn lost_update<S: Scheduler + 'static>(scheduler: S) -> Vec<usize> {
+    let mut lt = LogTest::default();
+    lt.set_strategy(BiasLatest::with_bias(1.0));
+    lt.set_scheduler(scheduler);
+
+    for _ in 0..2 {
+        lt.add(|mut eg: Environment| {
+            let v = eg.a.load(Ordering::SeqCst);
+            eg.a.store(v + 1, Ordering::SeqCst);
+            v
+        });
+    }
+
+    lt.run()
+}
+
+// With a depth of one there are no preemptions, so threads run to completion in priority order
+#[test]
+fn test_pct_depth_one() {
+    for _ in 0..200 {
+        let res = lost_update(PctScheduler::with_depth(1, 4));
+        assert!(res == vec![0, 1] || res == vec![1, 0]);
+    }
+}
+
+// The bug has depth two, and should be found with probability at least 1 / (2 * 4) per run
+#[test]
+fn test_pct_depth_two() {
+    assert!((0..200).any(|_| lost_update(PctScheduler::with_depth(2, 4)) == vec![0, 0]));
+}
+
+#[test]
+fn test_preemption_bound_zero() {
+    let (res, explorer) = explore_bounded(0, lost_update);
+
+    assert_eq!(res, HashSet::from([vec![0, 1], vec![1, 0]]));
+    assert_eq!(explorer.executions(), 2);
+    assert!(explorer.pruned());
+}
+
+#[test]
+fn test_preemption_bound_one() {
+    let (res, explorer) = explore_bounded(1, lost_update);
+
+    assert_eq!(res, HashSet::from([vec![0, 1], vec![1, 0], vec![0, 0]]));
+    assert!(explorer.pruned());
+}
+
+// Four steps can't contain more than three preemptions, so this covers every schedule
+#[test]
+fn test_preemption_bound_complete() {
+    let (res, explorer) = explore_bounded(3, lost_update);
+
+    assert_eq!(res, HashSet::from([vec![0, 1], vec![1, 0], vec![0, 0]]));
+    assert_eq!(explorer.executions(), 6);
+    assert!(!explorer.pruned());
+}
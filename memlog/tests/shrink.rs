@@ -0,0 +1,63 @@
+use crate::common::harness::{Environment, LogTest};
+use memlog::shrink::shrink;
+use memlog::strategy::Uniform;
+use memlog::trace::Trace;
+use sched::scheduler::UniformScheduler;
+use std::sync::atomic::Ordering;
+
+mod common;
+
+// A non atomic increment padded out with unrelated traffic on b
+fn noisy_lost_update(lt: &mut LogTest<usize>) -> bool {
+    for _ in 0..2 {
+        lt.add(|mut eg: Environment| {
+            for x in 0..5 {
+                eg.b.store(x, Ordering::Relaxed);
+                eg.b.load(Ordering::Relaxed);
+            }
+
+            let v = eg.a.load(Ordering::Relaxed);
+            eg.a.store(v + 1, Ordering::Relaxed);
+
+            for x in 0..5 {
+                eg.b.load(Ordering::Relaxed);
+                eg.b.store(x, Ordering::Relaxed);
+            }
+
+            v
+        });
+    }
+
+    lt.run() == vec![0, 0]
+}
+
+#[test]
+fn test_shrink() {
+    // A seed the update is lost with
+    let failing = {
+        let mut lt = LogTest::default();
+        lt.set_scheduler(UniformScheduler::new(0));
+        lt.set_strategy(Uniform::new(0));
+        let recording = lt.record();
+        assert!(noisy_lost_update(&mut lt));
+        recording.trace()
+    };
+
+    let shrunk = shrink(&failing, |guide| {
+        let mut lt = LogTest::default();
+        lt.guide(guide);
+        noisy_lost_update(&mut lt)
+    });
+
+    // The failure needs either a preemption or a stale read of a, and nothing else
+    assert_eq!(shrunk.preemptions() + shrunk.stale_loads(), 1);
+    assert!(
+        shrunk.preemptions() + shrunk.stale_loads() < failing.preemptions() + failing.stale_loads()
+    );
+
+    // The shrunk trace replays strictly
+    let mut lt = LogTest::default();
+    let replay = lt.replay(Trace::parse(&shrunk.to_string()).unwrap());
+    assert!(noisy_lost_update(&mut lt));
+    replay.finish();
+}
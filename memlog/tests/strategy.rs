@@ -0,0 +1,62 @@
+use crate::common::harness::{Environment, LogTest};
+use memlog::strategy::{BiasLatest, BiasOldest, Exhaustive};
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+
+mod common;
+
+fn two_stores_two_loads(lt: &mut LogTest<usize>) -> Vec<usize> {
+    lt.add(|mut eg: Environment| {
+        eg.a.store(1, Ordering::Relaxed);
+        eg.a.store(2, Ordering::Relaxed);
+        0
+    });
+
+    lt.add(|mut eg: Environment| {
+        let first = eg.a.load(Ordering::Relaxed);
+        let second = eg.a.load(Ordering::Relaxed);
+        first * 10 + second
+    });
+
+    lt.run_sequential()
+}
+
+#[test]
+fn test_bias_latest() {
+    for _ in 0..100 {
+        let mut lt = LogTest::default();
+        lt.set_strategy(BiasLatest::with_bias(1.0));
+        assert_eq!(two_stores_two_loads(&mut lt), vec![0, 22]);
+    }
+}
+
+#[test]
+fn test_bias_oldest() {
+    for _ in 0..100 {
+        let mut lt = LogTest::default();
+        lt.set_strategy(BiasOldest::with_bias(1.0));
+        assert_eq!(two_stores_two_loads(&mut lt), vec![0, 0]);
+    }
+}
+
+// Once a load has seen a store, later loads can't go backwards, so there are exactly six outcomes
+#[test]
+fn test_exhaustive() {
+    let driver = Exhaustive::default();
+    let mut runs = 0;
+    let mut results = HashSet::new();
+
+    loop {
+        let mut lt = LogTest::default();
+        lt.set_strategy(driver.strategy());
+        results.insert(two_stores_two_loads(&mut lt)[1]);
+        runs += 1;
+
+        if !driver.advance() {
+            break;
+        }
+    }
+
+    assert_eq!(runs, 6);
+    assert_eq!(results, HashSet::from([0, 1, 2, 11, 12, 22]));
+}
@@ -0,0 +1,145 @@
+use crate::common::harness::{Environment, LogTest};
+use memlog::trace::{Decision, Recording, Replay, Trace};
+use sched::scheduler::UniformScheduler;
+use std::sync::atomic::Ordering;
+use std::sync::{Arc, Mutex};
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+
+mod common;
+
+fn store_buffering(lt: &mut LogTest<usize>, extra_load: bool) -> Vec<usize> {
+    lt.add(|mut eg: Environment| {
+        eg.a.store(1, Ordering::Relaxed);
+        eg.b.load(Ordering::Relaxed)
+    });
+
+    lt.add(move |mut eg: Environment| {
+        eg.b.store(1, Ordering::Relaxed);
+        if extra_load {
+            eg.c.load(Ordering::Relaxed);
+        }
+        eg.a.load(Ordering::Relaxed)
+    });
+
+    lt.run()
+}
+
+#[test]
+fn test_trace_parse() {
+    let trace = Trace {
+        decisions: vec![
+            Decision::Schedule {
+                runnable: vec![0, 1, 3],
+                choice: 2,
+            },
+            Decision::Load {
+                options: 3,
+                choice: 1,
+            },
+        ],
+    };
+
+    assert_eq!(
+        trace.to_string(),
+        "# temper trace\nschedule 2 0,1,3\nload 1 3\n"
+    );
+    assert_eq!(Trace::parse(&trace.to_string()), Ok(trace));
+    assert!(Trace::parse("schedule x 0,1").is_err());
+}
+
+#[test]
+fn test_record_replay() {
+    let path = std::env::temp_dir().join(format!("memlog_trace_{}.txt", std::process::id()));
+
+    for _ in 0..20 {
+        let mut lt = LogTest::default();
+        let recording = lt.record();
+        let expected = store_buffering(&mut lt, false);
+        recording.trace().save(&path).unwrap();
+
+        for _ in 0..5 {
+            let mut lt = LogTest::default();
+            let replay = lt.replay(Trace::load(&path).unwrap());
+            assert_eq!(store_buffering(&mut lt, false), expected);
+            replay.finish();
+        }
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+#[should_panic(expected = "Replay diverged")]
+fn test_replay_divergence() {
+    let mut lt = LogTest::default();
+    let recording = lt.record();
+    store_buffering(&mut lt, false);
+
+    let mut lt = LogTest::default();
+    let replay = lt.replay(recording.trace());
+    store_buffering(&mut lt, true);
+    replay.finish();
+}
+
+// Threads reach the same waits either way, so a trace replays across backends
+#[test]
+fn test_replay_coroutines() {
+    for _ in 0..20 {
+        let mut lt = LogTest::default();
+        let recording = lt.record();
+        let expected = store_buffering(&mut lt, false);
+
+        let mut lt = LogTest::default();
+        lt.use_coroutines();
+        let replay = lt.replay(recording.trace());
+        assert_eq!(store_buffering(&mut lt, false), expected);
+        replay.finish();
+    }
+}
+
+// Two threads incrementing with a separate get and set, on temper's simulator
+fn lost_update(system: System) -> Vec<usize> {
+    let (counter, results) = (Arc::new(Atomic::new(0)), Arc::new(Mutex::new(vec![0; 2])));
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = (0..2)
+        .map(|i| {
+            let (counter, results) = (counter.clone(), results.clone());
+            Box::new(move || {
+                let v = *counter.get();
+                counter.set(v + 1);
+                results.lock().unwrap()[i] = v;
+            }) as Box<dyn FnMut() + Send>
+        })
+        .collect();
+
+    system.run(fns).unwrap();
+
+    let res = results.lock().unwrap().clone();
+    res
+}
+
+// Traces only hold scheduling decisions there, and replay the same way
+#[test]
+fn test_record_replay_temper() {
+    let path = std::env::temp_dir().join(format!("temper_trace_{}.txt", std::process::id()));
+
+    for _ in 0..20 {
+        let recording = Recording::default();
+        let system = System::with_scheduler(
+            MemoryModel::Intel,
+            recording.scheduler(UniformScheduler::default()),
+        );
+        let expected = lost_update(system);
+        recording.trace().save(&path).unwrap();
+
+        for _ in 0..5 {
+            let replay = Replay::new(Trace::load(&path).unwrap());
+            let system = System::with_scheduler(MemoryModel::Intel, replay.scheduler());
+            assert_eq!(lost_update(system), expected);
+            replay.finish();
+        }
+    }
+
+    std::fs::remove_file(&path).unwrap();
+}
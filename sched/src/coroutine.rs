@@ -0,0 +1,123 @@
+use crate::scheduler::Scheduler;
+use corosensei::{Coroutine, CoroutineResult, Yielder};
+use std::cell::Cell;
+use std::ptr::null;
+
+thread_local! {
+    // The yielder of the coroutine running on this thread, if there is one
+    static YIELDER: Cell<*const Yielder<(), ()>> = const { Cell::new(null()) };
+}
+
+// A simulated thread that runs on its driver's own OS thread, only when resumed
+pub struct Task<T: 'static> {
+    coroutine: Coroutine<(), (), T>,
+}
+
+impl<T: 'static> Task<T> {
+    pub fn new<F: FnOnce() -> T + 'static>(f: F) -> Task<T> {
+        Task {
+            coroutine: Coroutine::new(move |yielder, ()| {
+                YIELDER.with(|y| y.set(yielder));
+                f()
+            }),
+        }
+    }
+
+    // Runs the task until it suspends, or returns its result
+    pub fn resume(&mut self) -> Option<T> {
+        let res = self.coroutine.resume(());
+        YIELDER.with(|y| y.set(null()));
+
+        match res {
+            CoroutineResult::Yield(()) => None,
+            CoroutineResult::Return(v) => Some(v),
+        }
+    }
+}
+
+pub fn active() -> bool {
+    YIELDER.with(|y| !y.get().is_null())
+}
+
+// Hands control back to the driver until it next resumes this task
+pub fn suspend() {
+    let yielder = YIELDER.with(|y| y.get());
+    assert!(!yielder.is_null(), "suspend called outside a task");
+
+    // The yielder belongs to the task running this code, which is alive until it returns
+    unsafe { (*yielder).suspend(()) };
+
+    YIELDER.with(|y| y.set(yielder));
+}
+
+// Tasks sharing the calling thread, each resumed until it returns
+pub struct Tasks<T: 'static> {
+    tasks: Vec<Task<T>>,
+    results: Vec<Option<T>>,
+}
+
+impl<T: 'static> Default for Tasks<T> {
+    fn default() -> Self {
+        Tasks {
+            tasks: vec![],
+            results: vec![],
+        }
+    }
+}
+
+impl<T: 'static> Tasks<T> {
+    pub fn push(&mut self, task: Task<T>) {
+        self.tasks.push(task);
+        self.results.push(None);
+    }
+
+    pub fn len(&self) -> usize {
+        self.tasks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tasks.is_empty()
+    }
+
+    // Resumes the task, unless it has already returned
+    pub fn resume(&mut self, index: usize) {
+        if self.results[index].is_none() {
+            self.results[index] = self.tasks[index].resume();
+        }
+    }
+
+    // Resumes each task in turn, so every one runs until it suspends or returns
+    pub fn resume_all(&mut self) {
+        for index in 0..self.len() {
+            self.resume(index);
+        }
+    }
+
+    pub fn running(&self) -> Vec<usize> {
+        (0..self.len())
+            .filter(|&i| self.results[i].is_none())
+            .collect()
+    }
+
+    /*
+    Runs every task to the end. Each first runs up to its first suspend, as a thread does before
+    anything is scheduled, and then `scheduler` picks which to resume from those still running,
+    by the thread each one is in `threads`.
+    */
+    pub fn run(mut self, threads: &[usize], scheduler: &mut dyn Scheduler) -> Vec<T> {
+        self.resume_all();
+
+        loop {
+            let running = self.running();
+
+            if running.is_empty() {
+                break;
+            }
+
+            let runnable: Vec<usize> = running.iter().map(|&i| threads[i]).collect();
+            self.resume(running[scheduler.choose(&runnable)]);
+        }
+
+        self.results.into_iter().map(Option::unwrap).collect()
+    }
+}
@@ -0,0 +1,73 @@
+#[derive(Debug)]
+struct Branch<K> {
+    key: K,
+    // Choices in the order they're explored
+    order: Vec<usize>,
+    tried: usize,
+}
+
+/*
+The choices made across repeated runs, explored depth first. Each run starts from the root with
+`restart()` and makes its choices through `choose()`, and `advance()` moves to the next branch,
+returning false once every branch has been explored.
+*/
+#[derive(Debug)]
+pub struct DecisionTree<K> {
+    branches: Vec<Branch<K>>,
+    depth: usize,
+}
+
+impl<K> Default for DecisionTree<K> {
+    fn default() -> Self {
+        DecisionTree {
+            branches: vec![],
+            depth: 0,
+        }
+    }
+}
+
+impl<K: PartialEq> DecisionTree<K> {
+    pub fn restart(&mut self) {
+        self.depth = 0;
+    }
+
+    // The choice at the run's next decision. `key` describes what there was to choose from, and
+    // `order` gives the choices to explore, first one first, when the decision is new.
+    pub fn choose<F: FnOnce() -> Vec<usize>>(&mut self, key: K, order: F) -> usize {
+        let depth = self.depth;
+        self.depth += 1;
+
+        if self.branches.get(depth).is_some_and(|b| b.key != key) {
+            // The run diverged from the previous one, so restart this branch
+            self.branches.truncate(depth);
+        }
+
+        if self.branches.len() == depth {
+            self.branches.push(Branch {
+                key,
+                order: order(),
+                tried: 0,
+            });
+        }
+
+        let branch = &self.branches[depth];
+        branch.order[branch.tried]
+    }
+
+    pub fn advance(&mut self) -> bool {
+        // Anything past the depth reached on this run belongs to a different branch
+        let depth = self.depth;
+        self.branches.truncate(depth);
+
+        while let Some(last) = self.branches.last_mut() {
+            if last.tried + 1 < last.order.len() {
+                last.tried += 1;
+                return true;
+            }
+
+            self.branches.pop();
+        }
+
+        false
+    }
+}
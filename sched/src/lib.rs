@@ -0,0 +1,4 @@
+pub mod coroutine;
+pub mod explore;
+pub mod scheduler;
+pub mod seed;
@@ -0,0 +1,236 @@
+use crate::explore::DecisionTree;
+use crate::seed::time_seed;
+use rand::{Rng, RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+// Decides which candidate runs next. Each entry of `runnable` is the thread a candidate belongs to;
+// a thread may appear more than once when several of its operations can be reordered.
+// Returns an index into `runnable`.
+pub trait Scheduler: Send {
+    fn choose(&mut self, runnable: &[usize]) -> usize;
+}
+
+// Picks any candidate with equal probability at every step
+pub struct UniformScheduler {
+    rng: ChaCha8Rng,
+}
+
+impl UniformScheduler {
+    pub fn new(seed: u64) -> Self {
+        UniformScheduler {
+            rng: ChaCha8Rng::seed_from_u64(seed),
+        }
+    }
+}
+
+impl Default for UniformScheduler {
+    fn default() -> Self {
+        Self::new(time_seed())
+    }
+}
+
+impl Scheduler for UniformScheduler {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        (self.rng.next_u64() as usize) % runnable.len()
+    }
+}
+
+/*
+Probabilistic Concurrency Testing (Burckhardt et al, ASPLOS 2010)
+
+Every thread gets a random priority of at least `depth`, and the highest priority runnable thread
+always runs. At `depth - 1` randomly chosen steps the running thread is demoted below every other
+thread, forcing a preemption. A bug that needs `depth` ordering constraints is found with probability
+of at least 1 / (n * k^(depth - 1)) per run, for n threads and k steps.
+*/
+pub struct PctScheduler {
+    rng: ChaCha8Rng,
+    depth: usize,
+    step: usize,
+    change_points: Vec<usize>,
+    priorities: HashMap<usize, usize>,
+}
+
+impl PctScheduler {
+    // `max_steps` is an estimate of the number of scheduling decisions in a run
+    pub fn new(depth: usize, max_steps: usize, seed: u64) -> Self {
+        assert!(depth >= 1);
+        assert!(max_steps >= 1);
+
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut change_points = vec![];
+
+        while change_points.len() < (depth - 1).min(max_steps) {
+            let point = rng.gen_range(1..=max_steps);
+            if !change_points.contains(&point) {
+                change_points.push(point);
+            }
+        }
+
+        PctScheduler {
+            rng,
+            depth,
+            step: 0,
+            change_points,
+            priorities: HashMap::new(),
+        }
+    }
+
+    pub fn with_depth(depth: usize, max_steps: usize) -> Self {
+        Self::new(depth, max_steps, time_seed())
+    }
+
+    fn priority(&mut self, thread: usize) -> usize {
+        let depth = self.depth;
+        let rng = &mut self.rng;
+
+        *self
+            .priorities
+            .entry(thread)
+            .or_insert_with(|| depth + (rng.next_u32() as usize))
+    }
+}
+
+impl Scheduler for PctScheduler {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        self.step += 1;
+
+        let mut best = None;
+
+        for &thread in runnable {
+            let priority = self.priority(thread);
+
+            // Ties are broken towards the lower thread index
+            if best.is_none_or(|(bp, bt)| (priority, bt) > (bp, thread)) {
+                best = Some((priority, thread));
+            }
+        }
+
+        let (_, thread) = best.unwrap();
+
+        if let Some(i) = self.change_points.iter().position(|&p| p == self.step) {
+            self.priorities.insert(thread, self.depth - 1 - i);
+        }
+
+        // The thread may have several reorderable operations available
+        let candidates: Vec<usize> = (0..runnable.len())
+            .filter(|&i| runnable[i] == thread)
+            .collect();
+
+        candidates[(self.rng.next_u32() as usize) % candidates.len()]
+    }
+}
+
+// Decisions are told apart by the threads they chose between
+#[derive(Default, Debug)]
+struct BoundedState {
+    tree: DecisionTree<Vec<usize>>,
+    executions: usize,
+    pruned: bool,
+}
+
+/*
+Systematically explores every schedule with at most `bound` preemptions, as CHESS does.
+A preemption is switching away from a thread that could have kept running. Each run takes a
+fresh scheduler from `scheduler()`, and `advance()` moves to the next schedule, returning false
+once all of them have been explored. `pruned()` reports whether the bound cut any schedules off.
+
+Runs must be deterministic apart from scheduling, eg with a BiasLatest or Exhaustive load strategy.
+*/
+#[derive(Clone)]
+pub struct PreemptionBounded {
+    bound: usize,
+    state: Arc<Mutex<BoundedState>>,
+}
+
+pub struct BoundedScheduler {
+    bound: usize,
+    state: Arc<Mutex<BoundedState>>,
+    last: Option<usize>,
+    preemptions: usize,
+}
+
+impl PreemptionBounded {
+    pub fn new(bound: usize) -> Self {
+        PreemptionBounded {
+            bound,
+            state: Default::default(),
+        }
+    }
+
+    pub fn scheduler(&self) -> BoundedScheduler {
+        let mut state = self.state.lock().unwrap();
+        state.tree.restart();
+        state.executions += 1;
+
+        BoundedScheduler {
+            bound: self.bound,
+            state: self.state.clone(),
+            last: None,
+            preemptions: 0,
+        }
+    }
+
+    pub fn advance(&self) -> bool {
+        self.state.lock().unwrap().tree.advance()
+    }
+
+    pub fn executions(&self) -> usize {
+        self.state.lock().unwrap().executions
+    }
+
+    pub fn pruned(&self) -> bool {
+        self.state.lock().unwrap().pruned
+    }
+}
+
+impl BoundedScheduler {
+    fn is_preemption(&self, runnable: &[usize], thread: usize) -> bool {
+        match self.last {
+            Some(last) => last != thread && runnable.contains(&last),
+            None => false,
+        }
+    }
+}
+
+impl Scheduler for BoundedScheduler {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let state = &mut *state;
+
+        let choice = state.tree.choose(runnable.to_vec(), || {
+            let mut order: Vec<usize> = (0..runnable.len())
+                .filter(|&i| !self.is_preemption(runnable, runnable[i]))
+                .collect();
+
+            // Continuing the same thread comes first
+            order.sort_by_key(|&i| Some(runnable[i]) != self.last);
+
+            let preempting =
+                (0..runnable.len()).filter(|&i| self.is_preemption(runnable, runnable[i]));
+
+            if self.preemptions < self.bound {
+                order.extend(preempting);
+            } else if preempting.count() > 0 {
+                state.pruned = true;
+            }
+
+            order
+        });
+
+        if self.is_preemption(runnable, runnable[choice]) {
+            self.preemptions += 1;
+        }
+
+        self.last = Some(runnable[choice]);
+        choice
+    }
+}
+
+impl<S: Scheduler + ?Sized> Scheduler for Box<S> {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        (**self).choose(runnable)
+    }
+}
@@ -0,0 +1,4 @@
+// A seed for runs that weren't given one
+pub fn time_seed() -> u64 {
+    std::time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as u64
+}
@@ -1,14 +1,12 @@
 //#![warn(clippy::panic, clippy::unwrap_used, clippy::expect_used)]
 #![allow(clippy::ptr_arg)]
 
-use crate::temper::memory::core::{set_model, Atomic, MemoryModel};
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::Relaxed;
+use crate::temper::memory::core::{Atomic, MemoryModel};
 use std::sync::Arc;
+use std::thread;
 
-extern crate uuid;
 use crate::temper::system::core::System;
-use threadpool::ThreadPool;
+use crate::temper::system::parallel::ParallelRunner;
 
 pub mod temper;
 
@@ -32,10 +30,7 @@ fn test_right(t: &mut Test) {
     // println!("Got B {}", *res);
 }
 
-fn run_test() {
-    set_model(MemoryModel::Intel);
-    let s = System::new();
-
+fn run_test(s: System) {
     let t = Test {
         a: Arc::new(Atomic::new(0)),
         b: Arc::new(Atomic::new(0)),
@@ -49,30 +44,19 @@ fn run_test() {
         Box::new(move || test_right(&mut tb)),
     ];
 
-    s.run(fns);
+    s.run(fns).unwrap();
 }
 
 pub fn run_bench() {
     let now = std::time::SystemTime::now();
-    let n_workers = 1;
-    let pool = ThreadPool::new(n_workers);
-
-    let num = 1_000;
-    let fin = Arc::new(AtomicUsize::new(0));
-
-    for _ in 0..num {
-        let fin = fin.clone();
-        pool.execute(move || {
-            run_test();
-            fin.fetch_add(1, Relaxed);
-        });
-    }
-
-    pool.join();
+    let n_workers = thread::available_parallelism().map_or(1, |n| n.get());
 
-    while fin.load(Relaxed) != num {
-        std::thread::sleep(std::time::Duration::from_millis(1));
-    }
+    let outcomes = ParallelRunner::new(MemoryModel::Intel, n_workers).run(0..1_000, run_test);
 
-    println!("Done {:?}", now.elapsed().unwrap().as_millis());
+    println!(
+        "Done {} runs on {} workers in {:?}",
+        outcomes.runs(),
+        n_workers,
+        now.elapsed().unwrap().as_millis()
+    );
 }
@@ -0,0 +1,199 @@
+use crate::temper::memory::core::Atomic;
+use crate::temper::system::core::with_system;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/*
+Spinlocks built on the simulated atomics. The fences make them correct on every model, and each
+failed attempt to take the lock is counted against the thread that made it, so starvation and
+unfairness show up in the spin counts.
+*/
+pub trait Lock: Send + Sync {
+    fn lock(&self);
+    fn unlock(&self);
+
+    // How many times each thread has spun waiting for the lock
+    fn spins(&self) -> HashMap<usize, usize>;
+}
+
+#[derive(Default)]
+struct Spins {
+    counts: Mutex<HashMap<usize, usize>>,
+}
+
+impl Spins {
+    fn spin(&self) {
+        let thread = with_system(|s| s.thread);
+        *self.counts.lock().unwrap().entry(thread).or_default() += 1;
+    }
+
+    fn counts(&self) -> HashMap<usize, usize> {
+        self.counts.lock().unwrap().clone()
+    }
+}
+
+// Test-and-set: every attempt is a write, whether or not the lock is free
+#[derive(Default)]
+pub struct TasLock {
+    locked: Atomic<usize>,
+    spins: Spins,
+}
+
+impl Lock for TasLock {
+    fn lock(&self) {
+        while *self.locked.swap(1) == 1 {
+            self.spins.spin();
+        }
+
+        Atomic::<()>::fence();
+    }
+
+    fn unlock(&self) {
+        Atomic::<()>::fence();
+        self.locked.set(0);
+    }
+
+    fn spins(&self) -> HashMap<usize, usize> {
+        self.spins.counts()
+    }
+}
+
+// Test-and-test-and-set: waits with reads, and only writes once the lock looks free
+#[derive(Default)]
+pub struct TtasLock {
+    locked: Atomic<usize>,
+    spins: Spins,
+}
+
+impl Lock for TtasLock {
+    fn lock(&self) {
+        loop {
+            while *self.locked.get() == 1 {
+                self.spins.spin();
+            }
+
+            if *self.locked.swap(1) == 0 {
+                break;
+            }
+
+            self.spins.spin();
+        }
+
+        Atomic::<()>::fence();
+    }
+
+    fn unlock(&self) {
+        Atomic::<()>::fence();
+        self.locked.set(0);
+    }
+
+    fn spins(&self) -> HashMap<usize, usize> {
+        self.spins.counts()
+    }
+}
+
+// Threads take a ticket and are served in order, so the lock is fair
+#[derive(Default)]
+pub struct TicketLock {
+    next: Atomic<usize>,
+    serving: Atomic<usize>,
+    spins: Spins,
+}
+
+impl Lock for TicketLock {
+    fn lock(&self) {
+        let ticket = *self.next.fetch_add(1);
+
+        while *self.serving.get() != ticket {
+            self.spins.spin();
+        }
+
+        Atomic::<()>::fence();
+    }
+
+    fn unlock(&self) {
+        Atomic::<()>::fence();
+        self.serving.fetch_add(1);
+    }
+
+    fn spins(&self) -> HashMap<usize, usize> {
+        self.spins.counts()
+    }
+}
+
+struct McsNode {
+    locked: Atomic<usize>,
+    next: Atomic<usize>,
+}
+
+/*
+A queue lock. Each thread spins on its own node until its predecessor hands the lock over, rather
+than on a shared location. Nodes are indexed by thread, and 0 stands for no thread.
+*/
+pub struct McsLock {
+    tail: Atomic<usize>,
+    nodes: Vec<McsNode>,
+    spins: Spins,
+}
+
+impl McsLock {
+    pub fn new(threads: usize) -> Self {
+        McsLock {
+            tail: Atomic::new(0),
+            nodes: (0..=threads)
+                .map(|_| McsNode {
+                    locked: Atomic::new(0),
+                    next: Atomic::new(0),
+                })
+                .collect(),
+            spins: Spins::default(),
+        }
+    }
+}
+
+impl Lock for McsLock {
+    fn lock(&self) {
+        let thread = with_system(|s| s.thread);
+        let node = &self.nodes[thread];
+
+        node.next.set(0);
+        node.locked.set(1);
+        Atomic::<()>::fence();
+
+        let pred = *self.tail.swap(thread);
+
+        if pred != 0 {
+            self.nodes[pred].next.set(thread);
+
+            while *node.locked.get() == 1 {
+                self.spins.spin();
+            }
+        }
+
+        Atomic::<()>::fence();
+    }
+
+    fn unlock(&self) {
+        let thread = with_system(|s| s.thread);
+        let node = &self.nodes[thread];
+
+        Atomic::<()>::fence();
+
+        if *node.next.get() == 0 {
+            if self.tail.compare_exchange(thread, 0).is_ok() {
+                return;
+            }
+
+            // A successor has swapped itself in, but not linked itself yet
+            while *node.next.get() == 0 {
+                self.spins.spin();
+            }
+        }
+
+        self.nodes[*node.next.get()].locked.set(0);
+    }
+
+    fn spins(&self) -> HashMap<usize, usize> {
+        self.spins.counts()
+    }
+}
@@ -0,0 +1,87 @@
+use std::cell::RefCell;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub type Address = usize;
+
+pub const LINE_SIZE: usize = 64;
+
+/*
+Where allocations come from. Memory is never freed, so addresses in a space are unique for its
+life. The process-wide space is used by default. A run given a space of its own lays its memory
+out the same way whatever ran before it, which cache line sharing, and so outcomes, depend on.
+*/
+pub struct Space {
+    next: AtomicUsize,
+}
+
+impl Default for Space {
+    fn default() -> Self {
+        // Address 0 is left unused, like a null pointer
+        Space {
+            next: AtomicUsize::new(LINE_SIZE),
+        }
+    }
+}
+
+impl Space {
+    fn allocate(&self, size: usize, align: usize) -> Address {
+        let mut address = 0;
+        self.next
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |next| {
+                address = next.next_multiple_of(align);
+                Some(address + size)
+            })
+            .unwrap();
+
+        address
+    }
+}
+
+static GLOBAL: Space = Space {
+    next: AtomicUsize::new(LINE_SIZE),
+};
+
+thread_local! {
+    static SPACE: RefCell<Option<Arc<Space>>> = const { RefCell::new(None) };
+}
+
+// The space this thread allocates from, if it isn't the process-wide one
+fn space() -> Option<Arc<Space>> {
+    SPACE.with(|s| s.borrow().clone())
+}
+
+// Allocates from a space on the thread that entered it, until dropped
+pub struct Entered {
+    previous: Option<Arc<Space>>,
+}
+
+impl Drop for Entered {
+    fn drop(&mut self) {
+        let previous = self.previous.take();
+        SPACE.with(|s| *s.borrow_mut() = previous);
+    }
+}
+
+impl Space {
+    pub fn enter(self: &Arc<Self>) -> Entered {
+        Entered {
+            previous: SPACE.with(|s| s.borrow_mut().replace(self.clone())),
+        }
+    }
+}
+
+// Reserves `size` bytes aligned to `align`
+pub fn allocate(size: usize, align: usize) -> Address {
+    let size = size.max(1);
+    let align = align.max(1);
+
+    match space() {
+        Some(space) => space.allocate(size, align),
+        None => GLOBAL.allocate(size, align),
+    }
+}
+
+pub fn line(address: Address) -> usize {
+    address / LINE_SIZE
+}
@@ -0,0 +1,43 @@
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Access {
+    Read,
+    Write,
+    ReadWrite,
+}
+
+impl Access {
+    pub fn includes(&self, other: Access) -> bool {
+        *self == Access::ReadWrite || *self == other
+    }
+}
+
+// A barrier keeps accesses of the `before` kind that precede it ahead of accesses of the `after`
+// kind that follow it. This is the RISC-V `fence pred, succ` form, which the ARM barriers map onto.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Barrier {
+    pub before: Access,
+    pub after: Access,
+}
+
+impl Barrier {
+    // ARMv8 full barrier
+    pub const DMB_ISH: Barrier = Barrier::fence(Access::ReadWrite, Access::ReadWrite);
+    // ARMv8 load barrier: earlier loads before later loads and stores
+    pub const DMB_ISHLD: Barrier = Barrier::fence(Access::Read, Access::ReadWrite);
+    // ARMv8 store barrier: earlier stores before later stores
+    pub const DMB_ISHST: Barrier = Barrier::fence(Access::Write, Access::Write);
+
+    // Alpha barriers. Only mb makes later loads see past a thread's stale cache.
+    pub const MB: Barrier = Barrier::fence(Access::ReadWrite, Access::ReadWrite);
+    pub const WMB: Barrier = Barrier::fence(Access::Write, Access::Write);
+
+    pub const FENCE_RW_RW: Barrier = Barrier::fence(Access::ReadWrite, Access::ReadWrite);
+    pub const FENCE_R_RW: Barrier = Barrier::fence(Access::Read, Access::ReadWrite);
+    pub const FENCE_RW_W: Barrier = Barrier::fence(Access::ReadWrite, Access::Write);
+    pub const FENCE_R_R: Barrier = Barrier::fence(Access::Read, Access::Read);
+    pub const FENCE_W_W: Barrier = Barrier::fence(Access::Write, Access::Write);
+
+    pub const fn fence(before: Access, after: Access) -> Barrier {
+        Barrier { before, after }
+    }
+}
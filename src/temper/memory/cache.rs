@@ -0,0 +1,256 @@
+use crate::temper::memory::address::{line, Address, LINE_SIZE};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::sync::Mutex;
+
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum LineState {
+    Modified,
+    Exclusive,
+    Shared,
+    Invalid,
+}
+
+// Where an access found its line
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Fetch {
+    // Already held in a state that allows the access
+    Hit,
+    // No other core held it, so it came from memory
+    Memory,
+    // Taken from, or invalidated in, other cores' caches
+    Remote,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, Hash, Debug)]
+pub struct LineStats {
+    // Copies in other cores' caches invalidated by a write
+    pub invalidations: usize,
+    // Misses served by another core's cache rather than memory
+    pub transfers: usize,
+    // Writes that had to take ownership of the line first
+    pub rfos: usize,
+}
+
+impl LineStats {
+    pub fn contended(&self) -> bool {
+        self.invalidations + self.transfers > 0
+    }
+}
+
+// The addresses within a line one thread accessed
+#[derive(Clone, Default, Debug)]
+pub struct Touched {
+    pub reads: BTreeSet<Address>,
+    pub writes: BTreeSet<Address>,
+}
+
+impl Touched {
+    pub fn addresses(&self) -> BTreeSet<Address> {
+        self.reads.union(&self.writes).copied().collect()
+    }
+}
+
+#[derive(Clone, Default, Debug)]
+pub struct CacheProfile {
+    pub lines: BTreeMap<usize, LineStats>,
+    // For each line, what each thread touched in it
+    pub touched: BTreeMap<usize, BTreeMap<usize, Touched>>,
+}
+
+// A line two or more threads write to, although no two of them touch the same address
+#[derive(Clone, Debug)]
+pub struct FalseSharing {
+    pub line: usize,
+    pub stats: LineStats,
+    // The addresses each writing thread touched, which would be better on lines of their own
+    pub threads: BTreeMap<usize, BTreeSet<Address>>,
+}
+
+impl Display for FalseSharing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "line {:#x}: {} transfers, {} invalidations",
+            self.line * LINE_SIZE,
+            self.stats.transfers,
+            self.stats.invalidations
+        )?;
+
+        for (thread, addresses) in self.threads.iter() {
+            let offsets: Vec<String> = addresses
+                .iter()
+                .map(|a| format!("+{}", a % LINE_SIZE))
+                .collect();
+            writeln!(f, "  pad thread {}'s {}", thread, offsets.join(" "))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl CacheProfile {
+    pub fn total(&self) -> LineStats {
+        self.lines
+            .values()
+            .fold(LineStats::default(), |acc, s| LineStats {
+                invalidations: acc.invalidations + s.invalidations,
+                transfers: acc.transfers + s.transfers,
+                rfos: acc.rfos + s.rfos,
+            })
+    }
+
+    // Falsely shared lines, with the most coherence traffic first
+    pub fn false_sharing(&self) -> Vec<FalseSharing> {
+        let mut res = vec![];
+
+        for (&line, touched) in self.touched.iter() {
+            let writers: BTreeMap<usize, BTreeSet<Address>> = touched
+                .iter()
+                .filter(|(_, t)| !t.writes.is_empty())
+                .map(|(&thread, t)| (thread, t.addresses()))
+                .collect();
+
+            let sets: Vec<&BTreeSet<Address>> = writers.values().collect();
+            let disjoint = sets
+                .iter()
+                .enumerate()
+                .all(|(i, a)| sets[i + 1..].iter().all(|b| a.is_disjoint(b)));
+
+            if writers.len() >= 2 && disjoint {
+                res.push(FalseSharing {
+                    line,
+                    stats: self.lines[&line],
+                    threads: writers,
+                });
+            }
+        }
+
+        res.sort_by_key(|f| std::cmp::Reverse(f.stats.transfers));
+        res
+    }
+}
+
+impl Display for CacheProfile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "line invalidations transfers rfos")?;
+
+        for (line, s) in self.lines.iter() {
+            writeln!(f, "{} {} {} {}", line, s.invalidations, s.transfers, s.rfos)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct Line {
+    states: HashMap<usize, LineState>,
+    stats: LineStats,
+    touched: BTreeMap<usize, Touched>,
+}
+
+impl Line {
+    fn state(&self, core: usize) -> LineState {
+        *self.states.get(&core).unwrap_or(&LineState::Invalid)
+    }
+
+    fn others(&self, core: usize) -> impl Iterator<Item = usize> + '_ {
+        self.states
+            .iter()
+            .filter(move |(&c, &s)| c != core && s != LineState::Invalid)
+            .map(|(&c, _)| c)
+    }
+}
+
+/*
+MESI states for every line, per core, with a core for each thread. It only tracks where lines
+would be, and how often they'd move: values still come from the memory model.
+*/
+#[derive(Default)]
+pub struct Caches {
+    lines: Mutex<HashMap<usize, Line>>,
+}
+
+impl Caches {
+    pub fn read(&self, core: usize, address: Address) -> Fetch {
+        let mut lines = self.lines.lock().unwrap();
+        let line = lines.entry(line(address)).or_default();
+        line.touched.entry(core).or_default().reads.insert(address);
+
+        if line.state(core) != LineState::Invalid {
+            return Fetch::Hit;
+        }
+
+        let others: Vec<usize> = line.others(core).collect();
+
+        if others.is_empty() {
+            line.states.insert(core, LineState::Exclusive);
+            return Fetch::Memory;
+        }
+
+        line.stats.transfers += 1;
+
+        for other in others {
+            line.states.insert(other, LineState::Shared);
+        }
+
+        line.states.insert(core, LineState::Shared);
+        Fetch::Remote
+    }
+
+    pub fn write(&self, core: usize, address: Address) -> Fetch {
+        let mut lines = self.lines.lock().unwrap();
+        let line = lines.entry(line(address)).or_default();
+        line.touched.entry(core).or_default().writes.insert(address);
+
+        let fetch = match line.state(core) {
+            LineState::Modified => return Fetch::Hit,
+            LineState::Exclusive => Fetch::Hit,
+            state @ (LineState::Shared | LineState::Invalid) => {
+                let others: Vec<usize> = line.others(core).collect();
+                let fetch = match (state, others.is_empty()) {
+                    (_, false) => Fetch::Remote,
+                    (LineState::Invalid, true) => Fetch::Memory,
+                    _ => Fetch::Hit,
+                };
+
+                if line.state(core) == LineState::Invalid && !others.is_empty() {
+                    line.stats.transfers += 1;
+                }
+
+                line.stats.rfos += 1;
+                line.stats.invalidations += others.len();
+
+                for other in others {
+                    line.states.insert(other, LineState::Invalid);
+                }
+
+                fetch
+            }
+        };
+
+        line.states.insert(core, LineState::Modified);
+        fetch
+    }
+
+    pub fn state(&self, core: usize, line: usize) -> LineState {
+        self.lines
+            .lock()
+            .unwrap()
+            .get(&line)
+            .map_or(LineState::Invalid, |l| l.state(core))
+    }
+
+    pub fn profile(&self) -> CacheProfile {
+        let lines = self.lines.lock().unwrap();
+
+        CacheProfile {
+            lines: lines.iter().map(|(&line, l)| (line, l.stats)).collect(),
+            touched: lines
+                .iter()
+                .map(|(&line, l)| (line, l.touched.clone()))
+                .collect(),
+        }
+    }
+}
@@ -1,17 +1,42 @@
-use crate::temper::system::core::{with_system, Op, Operation};
+use crate::temper::memory::address::{allocate, line, Address, LINE_SIZE};
+use crate::temper::memory::barrier::{Access, Barrier};
+use crate::temper::memory::monitor::Monitor;
+use crate::temper::memory::store_buffer::StoreBuffer;
+use crate::temper::memory::view::{Snapshot, View, Write};
+use crate::temper::system::core::{submit, with_system, Category, Op, Resource};
+use crate::temper::system::cost::CostModel;
+use crate::temper::system::result::channel;
+pub use crate::temper::system::result::PendingResult;
 use crate::temper::utils::sleepwait::SleepWait;
 use std::any::Any;
-use std::cell::UnsafeCell;
-use std::ops::Deref;
-use std::rc::Rc;
-use std::sync::atomic::Ordering;
-use std::sync::{Arc, Mutex, MutexGuard};
-use uuid::Uuid;
-
-#[derive(Copy, Clone, PartialEq)]
+use std::ops::Add;
+use std::sync::{Arc, Mutex};
+
+#[derive(Copy, Clone, PartialEq, Debug)]
 pub enum MemoryModel {
     ARM,
     Intel,
+    // x86-TSO with an explicit store buffer per thread
+    TSO,
+    // ARMv8 and RISC-V weak ordering, with barrier kinds and acquire/release accesses
+    ARMv8,
+    RVWMO,
+    // POWER, where writes reach each thread independently rather than all at once
+    POWER,
+    // Alpha, where a thread's cache can hold stale copies of any location, even for dependent loads
+    Alpha,
+}
+
+impl MemoryModel {
+    pub const ALL: [MemoryModel; 7] = [
+        MemoryModel::ARM,
+        MemoryModel::Intel,
+        MemoryModel::TSO,
+        MemoryModel::ARMv8,
+        MemoryModel::RVWMO,
+        MemoryModel::POWER,
+        MemoryModel::Alpha,
+    ];
 }
 
 #[derive(Copy, Clone, PartialEq, Debug)]
@@ -19,31 +44,101 @@ pub enum MemoryOpType {
     Get,
     Set,
     Fence,
+    // Moves the oldest store in a thread's store buffer to memory
+    Flush,
+    // LDAR / STLR
+    GetAcquire,
+    SetRelease,
+    Barrier(Barrier),
+    // POWER lightweight sync. On other models it's a full fence.
+    LwSync,
+    // The half of a POWER lwsync that orders earlier stores before later ones
+    LwSyncStores,
+    // Makes a write visible to one other thread
+    Propagate,
+    // A LOCK prefixed read-modify-write
+    Rmw,
+    // LL/SC, which the remaining models build read-modify-writes from
+    LoadExclusive,
+    StoreExclusive,
+    // Loses a reservation, as an interrupt or eviction would, making a store exclusive fail
+    ClearExclusive,
 }
 
-thread_local! {
-    pub static MODEL: Mutex<Option<MemoryModel>> = const { Mutex::new(None) };
-}
+impl MemoryOpType {
+    pub fn access(&self) -> Option<Access> {
+        match self {
+            MemoryOpType::Get | MemoryOpType::GetAcquire | MemoryOpType::LoadExclusive => {
+                Some(Access::Read)
+            }
+            MemoryOpType::Set | MemoryOpType::SetRelease | MemoryOpType::StoreExclusive => {
+                Some(Access::Write)
+            }
+            _ => None,
+        }
+    }
 
-pub fn get_model() -> Option<MemoryModel> {
-    MODEL.with(|v| *v.lock().unwrap())
+    // Models without barrier kinds or acquire/release accesses treat them as the plain version
+    fn plain(self) -> MemoryOpType {
+        match self {
+            MemoryOpType::GetAcquire | MemoryOpType::LoadExclusive => MemoryOpType::Get,
+            MemoryOpType::SetRelease | MemoryOpType::StoreExclusive => MemoryOpType::Set,
+            MemoryOpType::Barrier(_) | MemoryOpType::LwSync | MemoryOpType::LwSyncStores => {
+                MemoryOpType::Fence
+            }
+            op => op,
+        }
+    }
+
+    // POWER has no acquire/release accesses, and other barriers are treated as a full sync
+    fn power(self) -> MemoryOpType {
+        match self {
+            MemoryOpType::LwSync | MemoryOpType::LwSyncStores => self,
+            op => op.plain(),
+        }
+    }
+
+    // Alpha has barrier kinds, but no acquire/release accesses
+    fn alpha(self) -> MemoryOpType {
+        match self {
+            MemoryOpType::Barrier(_) => self,
+            op => op.plain(),
+        }
+    }
+
+    // Barriers that order later loads have to discard stale cached values, like mb on Alpha
+    fn pulls(&self) -> bool {
+        match self {
+            MemoryOpType::Barrier(b) => b.after.includes(Access::Read),
+            _ => true,
+        }
+    }
 }
 
-pub fn set_model(model: MemoryModel) {
-    MODEL.with(|v| *v.lock().unwrap() = Some(model))
+// Memory state private to a single simulated thread
+#[derive(Default)]
+pub struct ThreadMemory {
+    pub store_buffer: StoreBuffer,
+    pub view: View,
+    // What the last lwsync saw, which later writes carry with them as they propagate
+    pub cumulative: Mutex<Option<Arc<Mutex<Snapshot>>>>,
+    pub monitor: Monitor,
 }
 
 pub struct MemoryOp {
     pub op: MemoryOpType,
+    model: MemoryModel,
     thread: usize,
-    location: Uuid,
+    location: Option<Address>,
     pub func: Box<dyn Fn() + Send>,
+    // The thread waiting on the result, if any
+    waiter: Option<Arc<SleepWait>>,
 }
 
 impl Op for MemoryOp {
     fn blocks(&self, other: &(dyn Op + Send)) -> bool {
         if let Some(other) = other.as_any().downcast_ref::<MemoryOp>() {
-            self.blocks(other, get_model().unwrap())
+            self.blocks(other, self.model)
         } else {
             false
         }
@@ -56,6 +151,41 @@ impl Op for MemoryOp {
     fn execute(&self) {
         (self.func)()
     }
+
+    fn abandon(&self) {
+        if let Some(waiter) = &self.waiter {
+            waiter.signal();
+        }
+    }
+
+    // Only full barriers order other subsystems' operations
+    fn category(&self) -> Category {
+        match self.op {
+            MemoryOpType::Fence => Category::Fence,
+            MemoryOpType::Barrier(b) if b == Barrier::DMB_ISH => Category::Fence,
+            _ => Category::Memory,
+        }
+    }
+
+    // The location, for subsystems that access memory directly, like DMA
+    fn resources(&self) -> Vec<Resource> {
+        let access = match self.op {
+            MemoryOpType::Rmw => Some(Access::ReadWrite),
+            op => op.access(),
+        };
+
+        match (self.location, access) {
+            (Some(location), Some(access)) => vec![Resource::new("memory", location, access)],
+            _ => vec![],
+        }
+    }
+
+    fn name(&self) -> String {
+        match self.location {
+            Some(location) => format!("{:?} {:#x}", self.op, location),
+            None => format!("{:?}", self.op),
+        }
+    }
 }
 
 impl MemoryOp {
@@ -66,44 +196,145 @@ impl MemoryOp {
             return false;
         }
 
-        if other.location == self.location {
+        if self.op == MemoryOpType::ClearExclusive || other.op == MemoryOpType::ClearExclusive {
+            return false;
+        }
+
+        // Operations execute in program order, apart from flushes, which can be overtaken.
+        // A flush can't overtake its own store, or earlier flushes.
+        if model == MemoryModel::TSO {
+            return match (self.op.plain(), other.op.plain()) {
+                (MemoryOpType::Set | MemoryOpType::Flush, MemoryOpType::Flush) => true,
+                (_, MemoryOpType::Flush) | (MemoryOpType::Flush, _) => false,
+                _ => true,
+            };
+        }
+
+        let same_location = self.location.is_some() && other.location == self.location;
+
+        if model == MemoryModel::POWER {
+            return Self::power_blocks(self.op.power(), other.op.power(), same_location);
+        }
+
+        if model == MemoryModel::Alpha {
+            return Self::alpha_blocks(self.op.alpha(), other.op.alpha(), same_location);
+        }
+
+        if same_location {
             return true;
         }
 
-        if model == MemoryModel::ARM && standard_op(self.op) && standard_op(other.op) {
+        if model == MemoryModel::ARMv8 || model == MemoryModel::RVWMO {
+            let lwsync = |o| match o {
+                MemoryOpType::LwSync => MemoryOpType::Fence,
+                o => o,
+            };
+            return Self::weak_blocks(lwsync(self.op), lwsync(other.op));
+        }
+
+        let (op, other_op) = (self.op.plain(), other.op.plain());
+
+        if model == MemoryModel::ARM && standard_op(op) && standard_op(other_op) {
             return false;
         }
 
         #[allow(clippy::match_like_matches_macro)]
-        match (&self.op, &other.op) {
+        match (op, other_op) {
             (MemoryOpType::Set, MemoryOpType::Get) => false,
             _ => true,
         }
     }
-}
 
-pub struct PendingResult<T: Copy> {
-    result: Arc<Mutex<Option<T>>>,
-    value: Rc<UnsafeCell<T>>,
-    sleep_wait: Arc<SleepWait>,
+    // Accesses to different locations are unordered unless a barrier or annotation says otherwise
+    fn weak_blocks(op: MemoryOpType, other: MemoryOpType) -> bool {
+        match (op, other) {
+            (MemoryOpType::Fence, _) | (_, MemoryOpType::Fence) => true,
+            // Nothing moves above an acquire or below a release, and release then acquire is RCsc
+            (MemoryOpType::GetAcquire, _) | (_, MemoryOpType::SetRelease) => true,
+            (MemoryOpType::SetRelease, MemoryOpType::GetAcquire) => true,
+            (MemoryOpType::Barrier(_), MemoryOpType::Barrier(_)) => true,
+            (MemoryOpType::Barrier(b), access) => {
+                access.access().is_some_and(|a| b.after.includes(a))
+            }
+            (access, MemoryOpType::Barrier(b)) => {
+                access.access().is_some_and(|a| b.before.includes(a))
+            }
+            _ => false,
+        }
+    }
+
+    /*
+    Visibility comes from propagation rather than execution order, so a write can be propagated
+    at any point after it executes. Barriers order execution, and the lwsync snapshot and the
+    sync push are what order propagation. Only a sync orders a store before a later load: an
+    lwsync doesn't wait for earlier stores, and its store half doesn't hold up later loads.
+    */
+    fn power_blocks(op: MemoryOpType, other: MemoryOpType, same_location: bool) -> bool {
+        match (op, other) {
+            (MemoryOpType::Set, MemoryOpType::Propagate) => same_location,
+            (MemoryOpType::Propagate, _) | (_, MemoryOpType::Propagate) => false,
+            (MemoryOpType::Fence, _) | (_, MemoryOpType::Fence) => true,
+            (MemoryOpType::Set, MemoryOpType::LwSync) => false,
+            (MemoryOpType::LwSyncStores, MemoryOpType::Get) => false,
+            (MemoryOpType::LwSync | MemoryOpType::LwSyncStores, _) => true,
+            (_, MemoryOpType::LwSync | MemoryOpType::LwSyncStores) => true,
+            _ => same_location,
+        }
+    }
+
+    // Execution is ordered as on ARMv8, but writes reach other threads' caches in any order
+    fn alpha_blocks(op: MemoryOpType, other: MemoryOpType, same_location: bool) -> bool {
+        match (op, other) {
+            (MemoryOpType::Set, MemoryOpType::Propagate) => same_location,
+            (MemoryOpType::Propagate, _) | (_, MemoryOpType::Propagate) => false,
+            _ => same_location || Self::weak_blocks(op, other),
+        }
+    }
 }
 
-pub struct Atomic<T: Copy> {
+pub struct Atomic<T> {
     value: Arc<Mutex<T>>,
-    id: Uuid,
+    address: Address,
+    size: usize,
 }
 
-pub struct SharedMemory<T: Copy> {
+pub struct SharedMemory<T> {
     arr: Vec<Atomic<T>>,
 }
 
-impl<T: Copy + Default + 'static + Send> SharedMemory<T> {
+impl<T: Clone + Default + Send + 'static> SharedMemory<T> {
+    // A contiguous array starting on a cache line, as large allocations do, so neighbouring
+    // elements share lines
     pub fn new(len: usize) -> Self {
+        let size = size_of::<T>().max(1);
+        let base = allocate(size * len, LINE_SIZE.max(align_of::<T>()));
+
         SharedMemory {
-            arr: (0..len).map(|_| Atomic::new(T::default())).collect(),
+            arr: (0..len)
+                .map(|i| Atomic::at(T::default(), base + i * size))
+                .collect(),
         }
     }
 
+    // Each element on its own cache line, so elements never contend
+    pub fn padded(len: usize) -> Self {
+        let base = allocate(LINE_SIZE * len, LINE_SIZE);
+
+        SharedMemory {
+            arr: (0..len)
+                .map(|i| Atomic::at(T::default(), base + i * LINE_SIZE))
+                .collect(),
+        }
+    }
+
+    pub fn address(&self, ind: usize) -> Address {
+        self.arr[ind].address()
+    }
+
+    pub fn size(&self, ind: usize) -> usize {
+        self.arr[ind].size()
+    }
+
     pub fn get(&self, ind: usize) -> PendingResult<T> {
         self.arr[ind].get()
     }
@@ -111,99 +342,533 @@ impl<T: Copy + Default + 'static + Send> SharedMemory<T> {
     pub fn set(&self, ind: usize, val: T) -> PendingResult<T> {
         self.arr[ind].set(val)
     }
+
+    pub fn load_exclusive(&self, ind: usize) -> PendingResult<T> {
+        self.arr[ind].load_exclusive()
+    }
+
+    pub fn store_exclusive(&self, ind: usize, val: T) -> PendingResult<bool> {
+        self.arr[ind].store_exclusive(val)
+    }
 }
 
-impl<T: Copy> Deref for PendingResult<T> {
-    type Target = T;
+impl<T: Clone + Default + Send + 'static> Default for Atomic<T> {
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
 
-    fn deref(&self) -> &T {
-        let mut taken = false;
+impl<T: Clone + Send + 'static> Atomic<T> {
+    pub fn new(value: T) -> Self {
+        Self::at(value, allocate(size_of::<T>(), align_of::<T>()))
+    }
 
-        while self.result.lock().unwrap().is_none() {
-            // We can't park if the value exists; this will cause race conditions
-            if !taken {
-                with_system(|s| s.parked.fetch_add(1, Ordering::SeqCst));
-                self.sleep_wait.wait();
-                taken = true;
+    // Places the atomic at an address that's already been allocated
+    pub fn at(value: T, address: Address) -> Self {
+        Self {
+            address,
+            size: size_of::<T>(),
+            value: Arc::new(Mutex::new(value)),
+        }
+    }
+
+    pub fn address(&self) -> Address {
+        self.address
+    }
+
+    // How many bytes from its address it takes up
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn line(&self) -> usize {
+        line(self.address)
+    }
+
+    // Operations without a location, such as barriers, only conflict through the model's rules
+    pub fn queue_op<F: Fn() + Send + 'static>(
+        location: Option<Address>,
+        op_type: MemoryOpType,
+        op: F,
+    ) {
+        Self::queue(location, op_type, None, op)
+    }
+
+    fn queue<F: Fn() + Send + 'static>(
+        location: Option<Address>,
+        op_type: MemoryOpType,
+        waiter: Option<Arc<SleepWait>>,
+        op: F,
+    ) {
+        let (thread, model) = with_system(|s| (s.thread, s.model));
+
+        submit(MemoryOp {
+            op: op_type,
+            model,
+            location,
+            thread,
+            func: Box::new(op),
+            waiter,
+        });
+    }
+
+    pub fn fence() {
+        Self::barrier_op(MemoryOpType::Fence);
+    }
+
+    // A platform specific barrier. Models without barrier kinds treat it as a full fence.
+    pub fn barrier(barrier: Barrier) {
+        Self::barrier_op(MemoryOpType::Barrier(barrier));
+    }
+
+    // On POWER, orders propagation of earlier writes, including those from other threads that
+    // this thread has seen, before propagation of later writes
+    pub fn lwsync() {
+        let (model, memory) = with_system(|s| (s.model, s.memory.clone()));
+        if model != MemoryModel::POWER {
+            let charge = Self::charge_drain(true, |c| c.fence);
+            return Self::queue_op(None, MemoryOpType::LwSync, move || {
+                charge(memory.store_buffer.drain());
+            });
+        }
+
+        let charge = Self::charge(true, |c| c.fence);
+
+        let snapshot = Arc::new(Mutex::new(Snapshot::default()));
+        *memory.cumulative.lock().unwrap() = Some(snapshot.clone());
+
+        // Two halves, so later loads only wait for earlier loads. The store half waits for
+        // everything before it, so its snapshot has the thread's own earlier stores.
+        Self::queue_op(None, MemoryOpType::LwSync, charge);
+        Self::queue_op(None, MemoryOpType::LwSyncStores, move || {
+            *snapshot.lock().unwrap() = memory.view.snapshot();
+        });
+    }
+
+    fn barrier_op(op: MemoryOpType) {
+        let (model, memory, memories, global) = with_system(|s| {
+            (
+                s.model,
+                s.memory.clone(),
+                s.memories.clone(),
+                s.global.clone(),
+            )
+        });
+
+        if model == MemoryModel::Alpha {
+            let charge = Self::charge(true, |c| c.fence);
+
+            // Alpha barriers don't make this thread's writes visible, they update its cache
+            return Self::queue_op(None, op, move || {
+                charge();
+                if op.pulls() {
+                    memory.view.merge(&global.snapshot());
+                }
+            });
+        }
+
+        let charge = Self::charge_drain(true, |c| c.fence);
+
+        Self::queue_op(None, op, move || {
+            charge(memory.store_buffer.drain());
+
+            // Everything this thread has seen reaches every thread before it continues
+            let snapshot = memory.view.snapshot();
+            for m in memories.iter() {
+                m.view.merge(&snapshot);
+            }
+        });
+    }
+
+    /*
+    Charges the calling thread when the returned closure runs, if the system keeps a clock.
+    Operations the program issued count towards its ops, while work they cause on the way, like
+    flushing a store buffer, only costs cycles.
+    */
+    fn charge(op: bool, cost: fn(&CostModel) -> usize) -> impl Fn() + Send + Sync + 'static {
+        let charge = Self::charge_drain(op, cost);
+        move || charge(0)
+    }
+
+    // As `charge`, for operations that drain the store buffer and pay for each store they drained
+    fn charge_drain(
+        op: bool,
+        cost: fn(&CostModel) -> usize,
+    ) -> impl Fn(usize) + Send + Sync + 'static {
+        let (thread, clock) = with_system(|s| (s.thread, s.clock.clone()));
+
+        move |drained| {
+            if let Some(clock) = &clock {
+                if op {
+                    clock.count(thread);
+                }
+
+                clock.charge(thread, cost(&clock.costs) + drained * clock.costs.drain);
             }
         }
+    }
+
+    pub fn self_op<R: Send + 'static, F: Fn() -> R + Send + 'static>(
+        &self,
+        op: MemoryOpType,
+        f: F,
+    ) -> PendingResult<R> {
+        let (completion, result) = channel();
+        let charge = Self::charge(true, |_| 0);
+        let waiter = completion.waiter();
+
+        Self::queue(Some(self.address), op, Some(waiter), move || {
+            charge();
+            completion.complete(f());
+        });
+
+        result
+    }
+
+    pub fn get(&self) -> PendingResult<T> {
+        self.load(MemoryOpType::Get)
+    }
+
+    pub fn get_acquire(&self) -> PendingResult<T> {
+        self.load(MemoryOpType::GetAcquire)
+    }
+
+    pub fn set(&self, val: T) -> PendingResult<T> {
+        self.store(MemoryOpType::Set, val)
+    }
 
-        if taken {
-            with_system(|s| s.parked.fetch_sub(1, Ordering::SeqCst));
+    pub fn set_release(&self, val: T) -> PendingResult<T> {
+        self.store(MemoryOpType::SetRelease, val)
+    }
+
+    fn load(&self, op: MemoryOpType) -> PendingResult<T> {
+        self.self_op(op, self.reader())
+    }
+
+    // Reads the location as the calling thread sees it under the current model
+    fn reader(&self) -> impl Fn() -> T + Send + 'static {
+        let id = self.address;
+        let value = self.value.clone();
+        let memory = with_system(|s| s.memory.clone());
+
+        let touch = self.touch();
+        let forwarded = Self::charge(false, |c| c.l1_hit);
+
+        move || {
+            // Forwarding from the store buffer doesn't reach the cache
+            if let Some(v) = memory.store_buffer.forward(id) {
+                forwarded();
+                return v;
+            }
+
+            touch(Access::Read);
+            memory
+                .view
+                .read(id)
+                .unwrap_or_else(|| value.lock().unwrap().clone())
+        }
+    }
+
+    // Records an access with the system's caches, if it tracks them, and charges what it cost
+    fn touch(&self) -> impl Fn(Access) + Send + Sync + 'static {
+        let address = self.address;
+        let (thread, caches, clock) =
+            with_system(|s| (s.thread, s.caches.clone(), s.clock.clone()));
+
+        move |access| {
+            if let Some(caches) = &caches {
+                let fetch = match access {
+                    Access::Read => caches.read(thread, address),
+                    _ => caches.write(thread, address),
+                };
+
+                if let Some(clock) = &clock {
+                    clock.charge(thread, clock.costs.access(fetch));
+                }
+            }
         }
+    }
 
-        let v = self.result.lock().unwrap();
+    // Every write takes ownership of the line, and clears reservations on it
+    fn writer(&self) -> impl Fn() + Send + Sync + 'static {
+        let line = self.line();
+        let touch = self.touch();
+        let memories = with_system(|s| s.memories.clone());
 
-        unsafe {
-            *self.value.get() = v.unwrap();
-            &*self.value.get()
+        move || {
+            touch(Access::Write);
+
+            for m in memories.iter() {
+                m.monitor.clear(line);
+            }
         }
     }
-}
 
-impl<T: Copy + Default + 'static + Send> Atomic<T> {
-    pub fn new(value: T) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            value: Arc::new(Mutex::new(value)),
+    fn store(&self, op: MemoryOpType, val: T) -> PendingResult<T> {
+        let id = self.address;
+        let value = self.value.clone();
+        let (model, memory) = with_system(|s| (s.model, s.memory.clone()));
+
+        if model != MemoryModel::TSO {
+            let (commit, propagate) = self.commit(val.clone());
+            let res = self.self_op(op, move || {
+                commit();
+                val.clone()
+            });
+            propagate();
+            return res;
         }
+
+        let after_write = Arc::new(self.writer());
+
+        let res = {
+            let memory = memory.clone();
+
+            self.self_op(op, move || {
+                let value = value.clone();
+                let stored = val.clone();
+                let after_write = after_write.clone();
+                memory.store_buffer.push(id, val.clone(), move || {
+                    *value.lock().unwrap() = stored;
+                    after_write();
+                });
+                val.clone()
+            })
+        };
+
+        Self::queue_op(Some(id), MemoryOpType::Flush, move || {
+            memory.store_buffer.flush_one()
+        });
+
+        res
+    }
+
+    /*
+    What a store does when it executes, and a second step queueing anything that has to follow it.
+    Under POWER and Alpha the write reaches this thread when it executes, and every other thread
+    when its propagate op does. Memory itself keeps the initial value, for threads nothing has
+    reached yet.
+    */
+    #[allow(clippy::type_complexity)]
+    fn commit(&self, val: T) -> (Box<dyn Fn() + Send>, Box<dyn FnOnce()>) {
+        let id = self.address;
+        let (model, memory, memories, global) = with_system(|s| {
+            (
+                s.model,
+                s.memory.clone(),
+                s.memories.clone(),
+                s.global.clone(),
+            )
+        });
+
+        let after_write = self.writer();
+
+        if model != MemoryModel::POWER && model != MemoryModel::Alpha {
+            let value = self.value.clone();
+
+            return (
+                Box::new(move || {
+                    *value.lock().unwrap() = val.clone();
+                    after_write();
+                }),
+                Box::new(|| {}),
+            );
+        }
+
+        let cumulative = memory.cumulative.lock().unwrap().clone();
+        let write = Arc::new(Mutex::new(None));
+
+        let commit = {
+            let memory = memory.clone();
+            let write = write.clone();
+
+            move || {
+                let w = Write::new(val.clone());
+                memory.view.write(id, w.clone());
+                global.write(id, w.clone());
+                *write.lock().unwrap() = Some(w);
+                after_write();
+            }
+        };
+
+        let propagate = move || {
+            for target in memories.iter().filter(|m| !Arc::ptr_eq(m, &memory)) {
+                let target = target.clone();
+                let write = write.clone();
+                let cumulative = cumulative.clone();
+
+                Self::queue_op(Some(id), MemoryOpType::Propagate, move || {
+                    // A store exclusive that failed has nothing to propagate
+                    let Some(w) = write.lock().unwrap().clone() else {
+                        return;
+                    };
+
+                    if let Some(snapshot) = &cumulative {
+                        target.view.merge(&snapshot.lock().unwrap());
+                    }
+
+                    target.view.write(id, w);
+                });
+            }
+        };
+
+        (Box::new(commit), Box::new(propagate))
     }
 
-    pub fn queue_op<F: Fn() + Send + 'static>(id: Uuid, op_type: MemoryOpType, op: F) {
-        let op = {
-            Operation::build(MemoryOp {
-                op: op_type,
-                location: id,
-                thread: with_system(|s| s.thread),
-                func: Box::new(op),
+    // Load-linked. The reservation it takes can be lost at any point, unless spurious failures
+    // are turned off, and is lost whenever another thread writes to the same cache line.
+    pub fn load_exclusive(&self) -> PendingResult<T> {
+        let spurious = with_system(|s| s.spurious_failures);
+        self.load_linked(spurious)
+    }
+
+    /*
+    Store-conditional. Writes only if the reservation is still held, and returns whether it was.
+    It writes straight to memory, so under TSO the store buffer is drained first, or an older
+    buffered store could land on top of it.
+    */
+    pub fn store_exclusive(&self, val: T) -> PendingResult<bool> {
+        let line = self.line();
+        let memory = with_system(|s| s.memory.clone());
+        let (commit, propagate) = self.commit(val);
+
+        let res = self.self_op(MemoryOpType::StoreExclusive, move || {
+            memory.store_buffer.drain();
+            let held = memory.monitor.take(line);
+
+            if held {
+                commit();
+            }
+
+            held
+        });
+
+        propagate();
+        res
+    }
+
+    // Reads the coherent value, as the reservation would be meaningless against a stale one
+    fn load_linked(&self, spurious: bool) -> PendingResult<T> {
+        let (id, line) = (self.address, self.line());
+        let read = self.reader();
+        let (memory, global) = with_system(|s| (s.memory.clone(), s.global.clone()));
+        let reservation = Monitor::reservation();
+
+        let res = {
+            let memory = memory.clone();
+
+            self.self_op(MemoryOpType::LoadExclusive, move || {
+                // The thread's own buffered stores go out before it reserves the line, rather
+                // than clearing the reservation when the store exclusive drains them
+                memory.store_buffer.drain();
+
+                if let Some(w) = global.latest(id) {
+                    memory.view.write(id, w);
+                }
+
+                memory.monitor.reserve(line, reservation);
+                read()
             })
         };
 
-        with_system(move |s| s.chan.send(op).unwrap());
+        if spurious {
+            Self::queue_op(None, MemoryOpType::ClearExclusive, move || {
+                memory.monitor.release(reservation)
+            });
+        }
+
+        res
     }
 
-    pub fn fence() {
-        Self::queue_op(Uuid::new_v4(), MemoryOpType::Fence, move || {});
+    fn locked(&self) -> bool {
+        let model = with_system(|s| s.model);
+        model == MemoryModel::Intel || model == MemoryModel::TSO
     }
 
-    pub fn self_op<F: Fn(MutexGuard<T>) -> T + Send + 'static>(
+    /*
+    Updates the location atomically. `f` maps the current value to the value to write, if any, and
+    the result. Intel does this in one LOCK prefixed op, which drains the store buffer and orders
+    everything around it. Other models loop on LL/SC until the store exclusive succeeds. The first
+    attempt can fail spuriously, but later ones only fail when another thread writes in between.
+    */
+    fn rmw<R: Send + 'static, F: Fn(T) -> (Option<T>, R) + Send + 'static>(
         &self,
-        op: MemoryOpType,
         f: F,
-    ) -> PendingResult<T> {
-        let value = Rc::new(UnsafeCell::new(T::default()));
+    ) -> PendingResult<R> {
+        if self.locked() {
+            let value = self.value.clone();
+            let memory = with_system(|s| s.memory.clone());
+            let after_write = self.writer();
+            let lock = Self::charge_drain(false, |c| c.locked_rmw);
+
+            // The line is taken exclusively even if nothing is written
+            return self.self_op(MemoryOpType::Rmw, move || {
+                lock(memory.store_buffer.drain());
+                after_write();
+
+                let mut value = value.lock().unwrap();
+                let (new, res) = f(value.clone());
+
+                if let Some(new) = new {
+                    *value = new;
+                }
+
+                res
+            });
+        }
 
-        let vclone = self.value.clone();
-        let result = Arc::new(Mutex::new(None));
-        let sleep_wait = Arc::new(SleepWait::default());
+        let mut spurious = with_system(|s| s.spurious_failures);
 
-        {
-            let value_slot = result.clone();
-            let sleep_wait = sleep_wait.clone();
+        loop {
+            let current = self.load_linked(spurious);
+            spurious = false;
 
-            Self::queue_op(self.id, op, move || {
-                let v = vclone.lock().unwrap();
+            let (new, res) = f(current.wait());
 
-                *value_slot.lock().unwrap() = Some(f(v));
-                sleep_wait.signal();
-            });
-        }
+            if let Some(new) = new {
+                if !*self.store_exclusive(new) {
+                    continue;
+                }
+            }
 
-        PendingResult {
-            value,
-            result,
-            sleep_wait,
+            return PendingResult::ready(res);
         }
     }
 
-    pub fn get(&self) -> PendingResult<T> {
-        self.self_op(MemoryOpType::Get, move |v| *v)
+    pub fn swap(&self, val: T) -> PendingResult<T> {
+        self.rmw(move |old| (Some(val.clone()), old))
     }
+}
 
-    pub fn set(&self, val: T) -> PendingResult<T> {
-        self.self_op(MemoryOpType::Set, move |mut v| {
-            *v = val;
-            val
+impl<T: Clone + PartialEq + Send + 'static> Atomic<T> {
+    pub fn compare_exchange(&self, current: T, new: T) -> PendingResult<Result<T, T>> {
+        self.rmw(move |old| {
+            if old == current {
+                (Some(new.clone()), Ok(old))
+            } else {
+                (None, Err(old))
+            }
         })
     }
+
+    // A single LL/SC attempt, which can fail even when the value matches
+    pub fn compare_exchange_weak(&self, current: T, new: T) -> PendingResult<Result<T, T>> {
+        if self.locked() {
+            return self.compare_exchange(current, new);
+        }
+
+        let old = self.load_exclusive().wait();
+
+        if old != current || !*self.store_exclusive(new) {
+            return PendingResult::ready(Err(old));
+        }
+
+        PendingResult::ready(Ok(old))
+    }
+}
+
+impl<T: Clone + Add<Output = T> + Send + 'static> Atomic<T> {
+    pub fn fetch_add(&self, val: T) -> PendingResult<T> {
+        self.rmw(move |old| (Some(old.clone() + val.clone()), old))
+    }
 }
@@ -1 +1,7 @@
+pub mod address;
+pub mod barrier;
+pub mod cache;
 pub mod core;
+pub mod monitor;
+pub mod store_buffer;
+pub mod view;
@@ -0,0 +1,53 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+static RESERVATIONS: AtomicUsize = AtomicUsize::new(0);
+
+struct Reservation {
+    line: usize,
+    id: usize,
+}
+
+// A thread's exclusive monitor, for LL/SC. It holds at most one reservation, on a whole cache
+// line, which any write to the line clears.
+#[derive(Default)]
+pub struct Monitor {
+    reservation: Mutex<Option<Reservation>>,
+}
+
+impl Monitor {
+    // Identifies a reservation, so losing it spuriously can't affect a later one
+    pub fn reservation() -> usize {
+        RESERVATIONS.fetch_add(1, Ordering::SeqCst)
+    }
+
+    pub fn reserve(&self, line: usize, id: usize) {
+        *self.reservation.lock().unwrap() = Some(Reservation { line, id });
+    }
+
+    // Consumes the reservation, returning whether it covered the line
+    pub fn take(&self, line: usize) -> bool {
+        self.reservation
+            .lock()
+            .unwrap()
+            .take()
+            .is_some_and(|r| r.line == line)
+    }
+
+    // Loses one particular reservation, if it's still held
+    pub fn release(&self, id: usize) {
+        let mut reservation = self.reservation.lock().unwrap();
+
+        if reservation.as_ref().is_some_and(|r| r.id == id) {
+            *reservation = None;
+        }
+    }
+
+    pub fn clear(&self, line: usize) {
+        let mut reservation = self.reservation.lock().unwrap();
+
+        if reservation.as_ref().is_some_and(|r| r.line == line) {
+            *reservation = None;
+        }
+    }
+}
@@ -0,0 +1,71 @@
+use crate::temper::memory::address::Address;
+use std::any::Any;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+struct BufferedStore {
+    location: Address,
+    value: Box<dyn Any + Send>,
+    commit: Box<dyn FnOnce() + Send>,
+}
+
+// A per-thread FIFO of stores that have executed but aren't yet visible to other threads (x86-TSO)
+#[derive(Default)]
+pub struct StoreBuffer {
+    stores: Mutex<VecDeque<BufferedStore>>,
+}
+
+impl StoreBuffer {
+    pub fn push<T: Send + 'static, F: FnOnce() + Send + 'static>(
+        &self,
+        location: Address,
+        value: T,
+        commit: F,
+    ) {
+        self.stores.lock().unwrap().push_back(BufferedStore {
+            location,
+            value: Box::new(value),
+            commit: Box::new(commit),
+        });
+    }
+
+    // Loads read the newest buffered store to their own location, if there is one
+    pub fn forward<T: Clone + 'static>(&self, location: Address) -> Option<T> {
+        self.stores
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .find(|s| s.location == location)
+            .map(|s| s.value.downcast_ref::<T>().unwrap().clone())
+    }
+
+    // Makes the oldest buffered store visible to every thread
+    pub fn flush_one(&self) {
+        let store = self.stores.lock().unwrap().pop_front();
+
+        if let Some(store) = store {
+            (store.commit)();
+        }
+    }
+
+    // Flushes every buffered store, returning how many there were
+    pub fn drain(&self) -> usize {
+        let mut drained = 0;
+
+        while !self.is_empty() {
+            self.flush_one();
+            drained += 1;
+        }
+
+        drained
+    }
+
+    pub fn len(&self) -> usize {
+        self.stores.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
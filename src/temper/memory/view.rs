@@ -0,0 +1,68 @@
+use crate::temper::memory::address::Address;
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Only the relative order of writes matters, so a single counter serves every system
+static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+// A write, numbered in coherence order
+#[derive(Clone)]
+pub struct Write {
+    version: usize,
+    value: Arc<Mutex<dyn Any + Send>>,
+}
+
+impl Write {
+    pub fn new<T: Send + 'static>(value: T) -> Self {
+        Write {
+            version: WRITES.fetch_add(1, Ordering::SeqCst),
+            value: Arc::new(Mutex::new(value)),
+        }
+    }
+}
+
+pub type Snapshot = HashMap<Address, Write>;
+
+// The newest write to each location that has reached a thread, for non-multicopy-atomic models
+#[derive(Default)]
+pub struct View {
+    writes: Mutex<Snapshot>,
+}
+
+impl View {
+    pub fn read<T: Clone + 'static>(&self, location: Address) -> Option<T> {
+        self.writes
+            .lock()
+            .unwrap()
+            .get(&location)
+            .map(|w| w.value.lock().unwrap().downcast_ref::<T>().unwrap().clone())
+    }
+
+    pub fn latest(&self, location: Address) -> Option<Write> {
+        self.writes.lock().unwrap().get(&location).cloned()
+    }
+
+    // A write that arrives after a newer one to the same location is never seen
+    pub fn write(&self, location: Address, write: Write) {
+        let mut writes = self.writes.lock().unwrap();
+
+        if writes
+            .get(&location)
+            .is_none_or(|w| w.version < write.version)
+        {
+            writes.insert(location, write);
+        }
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        self.writes.lock().unwrap().clone()
+    }
+
+    pub fn merge(&self, snapshot: &Snapshot) {
+        for (location, write) in snapshot.iter() {
+            self.write(*location, write.clone());
+        }
+    }
+}
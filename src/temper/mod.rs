@@ -1,3 +1,4 @@
+pub mod locks;
 pub mod memory;
 pub mod system;
 pub mod utils;
@@ -1,18 +1,84 @@
-use rand::{RngCore, SeedableRng};
-use rand_chacha::ChaCha8Rng;
+use crate::temper::memory::address::{Entered, Space};
+use crate::temper::memory::barrier::Access;
+use crate::temper::memory::cache::{CacheProfile, Caches};
+use crate::temper::memory::core::{MemoryModel, ThreadMemory};
+use crate::temper::memory::view::View;
+use crate::temper::system::coroutine::{Task, Tasks};
+use crate::temper::system::cost::{Clock, CostModel, Cycles};
+use crate::temper::system::time::{Instant, VirtualTime};
+use sched::scheduler::{Scheduler, UniformScheduler};
+use sched::seed::time_seed;
 use std::any::Any;
-use std::sync::atomic::AtomicUsize;
-use std::sync::atomic::Ordering::SeqCst;
+use std::fmt::{Debug, Display, Formatter};
+use std::panic::{catch_unwind, resume_unwind, AssertUnwindSafe};
 use std::sync::mpsc::{channel, Sender};
 use std::sync::Arc;
-use std::sync::Mutex;
+use std::sync::{Condvar, Mutex};
 use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
 
 #[derive(Clone)]
 pub struct SystemInfo {
     pub thread: usize,
     pub chan: Sender<Operation>,
-    pub parked: Arc<AtomicUsize>,
+    pub activity: Arc<Activity>,
+    pub model: MemoryModel,
+    pub spurious_failures: bool,
+    pub memory: Arc<ThreadMemory>,
+    pub memories: Arc<Vec<Arc<ThreadMemory>>>,
+    // Every write that has executed, for models where threads can lag behind it
+    pub global: Arc<View>,
+    pub caches: Option<Arc<Caches>>,
+    pub clock: Option<Arc<Clock>>,
+    pub space: Option<Arc<Space>>,
+    pub time: Arc<VirtualTime>,
+}
+
+#[derive(Copy, Clone, Default, PartialEq, Eq, Debug)]
+pub struct Counts {
+    // Waiting on an operation the scheduler hasn't executed
+    pub parked: usize,
+    pub finished: usize,
+}
+
+impl Counts {
+    pub fn idle(&self) -> usize {
+        self.parked + self.finished
+    }
+}
+
+// What the simulated threads are doing. The scheduler sleeps on it until it has to act.
+#[derive(Default)]
+pub struct Activity {
+    counts: Mutex<Counts>,
+    changed: Condvar,
+}
+
+impl Activity {
+    pub fn park(&self) {
+        self.update(|c| c.parked += 1);
+    }
+
+    pub fn unpark(&self) {
+        self.update(|c| c.parked -= 1);
+    }
+
+    pub fn finish(&self) {
+        self.update(|c| c.finished += 1);
+    }
+
+    fn update<F: FnOnce(&mut Counts)>(&self, f: F) {
+        f(&mut self.counts.lock().unwrap());
+        self.changed.notify_all();
+    }
+
+    pub fn wait_until<F: Fn(Counts) -> bool>(&self, f: F) -> Counts {
+        *self
+            .changed
+            .wait_while(self.counts.lock().unwrap(), |c| !f(*c))
+            .unwrap()
+    }
 }
 
 thread_local! {
@@ -23,98 +89,509 @@ pub fn with_system<T, F: FnOnce(&SystemInfo) -> T>(f: F) -> T {
     SYSTEM.with(|a| f(a.lock().unwrap().as_ref().unwrap()))
 }
 
+// Queues an operation for the calling thread
+pub fn submit<T: 'static + Op + Send>(op: T) {
+    with_system(|s| s.chan.send(Operation::build(s.thread, op)).unwrap());
+}
+
+// The kind of work an operation does, so subsystems can order against ones they don't know about
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum Category {
+    Memory,
+    // Orders every operation of its thread around it, whatever subsystem it belongs to
+    Fence,
+    Io,
+    Time,
+    Other,
+}
+
+// Something an operation reads or writes outside the subsystem's own rules, such as a disk block
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Resource {
+    pub kind: &'static str,
+    pub id: usize,
+    pub access: Access,
+}
+
+impl Resource {
+    pub fn new(kind: &'static str, id: usize, access: Access) -> Self {
+        Resource { kind, id, access }
+    }
+
+    pub fn conflicts(&self, other: &Resource) -> bool {
+        self.kind == other.kind
+            && self.id == other.id
+            && (self.access.includes(Access::Write) || other.access.includes(Access::Write))
+    }
+}
+
+/*
+An operation a thread queues for the scheduler to execute. `blocks` holds the subsystem's own
+rules, and is only asked about operations of the same type. Operations of different types on the
+same thread are ordered when either is a fence, or their resources conflict.
+*/
 pub trait Op {
     fn blocks(&self, other: &(dyn Op + Send)) -> bool;
     fn as_any(&self) -> &dyn Any;
     fn execute(&self);
+    // The run is being torn down, and the operation will never execute. Anything waiting on it
+    // has to be woken.
+    fn abandon(&self) {}
+
+    fn category(&self) -> Category {
+        Category::Other
+    }
+
+    fn resources(&self) -> Vec<Resource> {
+        vec![]
+    }
+
+    // Operations with a deadline can't execute before it, in virtual time
+    fn deadline(&self) -> Option<Instant> {
+        None
+    }
+
+    // What the operation is, for logs
+    fn name(&self) -> String {
+        format!("{:?}", self.category())
+    }
 }
 
 pub struct Operation {
+    pub thread: usize,
     pub op: Box<dyn Op + Send>,
 }
 
 impl Operation {
-    pub fn build<T: 'static + Op + Send>(op: T) -> Operation {
-        Operation { op: Box::new(op) }
+    pub fn build<T: 'static + Op + Send>(thread: usize, op: T) -> Operation {
+        Operation {
+            thread,
+            op: Box::new(op),
+        }
     }
 
     pub fn execute(&self) {
         self.op.execute();
     }
+
+    pub fn abandon(&self) {
+        self.op.abandon();
+    }
+
+    // Whether this operation, queued earlier, has to execute before `later`
+    pub fn blocks(&self, later: &Operation) -> bool {
+        if self.thread != later.thread {
+            return false;
+        }
+
+        if self.op.as_any().type_id() == later.op.as_any().type_id() {
+            return self.op.blocks(later.op.as_ref());
+        }
+
+        // Operations waiting on the clock aren't waiting on their thread, so fences don't wait
+        // for them to fire
+        if self.op.deadline().is_some() || later.op.deadline().is_some() {
+            return false;
+        }
+
+        if self.op.category() == Category::Fence || later.op.category() == Category::Fence {
+            return true;
+        }
+
+        let resources = later.op.resources();
+        self.op
+            .resources()
+            .iter()
+            .any(|r| resources.iter().any(|o| r.conflicts(o)))
+    }
 }
 
-#[derive(Default)]
-pub struct System {}
+// An operation that executed, in the order the scheduler chose
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Executed {
+    pub thread: usize,
+    pub category: Category,
+    pub name: String,
+}
 
-impl System {
-    pub fn new() -> Self {
-        Self {}
+// The payload threads unwind with when they're torn down, rather than panicking themselves
+pub struct TornDown;
+
+// The thread a panic is attributed to when the scheduler panicked, rather than a simulated thread
+pub const SCHEDULER: usize = 0;
+
+// A thread of the run panicked, or an operation it queued did, or the scheduler did
+pub struct Panic {
+    pub thread: usize,
+    pub payload: Box<dyn Any + Send>,
+    // The seed of the run's scheduler, if the system chose it
+    pub seed: Option<u64>,
+}
+
+impl Panic {
+    pub fn message(&self) -> Option<&str> {
+        self.payload
+            .downcast_ref::<&str>()
+            .copied()
+            .or_else(|| self.payload.downcast_ref::<String>().map(|s| s.as_str()))
     }
 
-    pub fn get_op(&self, ops: &mut Vec<Operation>, ind: usize) -> Option<Operation> {
-        if ops.is_empty() {
-            return None;
+    // Panics again on the calling thread, with the original payload
+    pub fn resume(self) -> ! {
+        resume_unwind(self.payload)
+    }
+}
+
+impl Display for Panic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let message = self.message().unwrap_or("Box<dyn Any>");
+
+        if self.thread == SCHEDULER {
+            write!(f, "scheduler panicked: {}", message)?;
+        } else {
+            write!(f, "thread {} panicked: {}", self.thread, message)?;
         }
 
-        let ind = ind % ops.len();
+        if let Some(seed) = self.seed {
+            write!(f, " (seed {})", seed)?;
+        }
 
-        for x in 0..ind {
-            if ops[x].op.blocks(ops[ind].op.as_ref()) {
-                return None;
-            }
+        Ok(())
+    }
+}
+
+impl Debug for Panic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+pub struct System {
+    model: MemoryModel,
+    scheduler: Box<dyn Scheduler>,
+    caches: bool,
+    costs: Option<CostModel>,
+    seed: Option<u64>,
+    coroutines: bool,
+    log: bool,
+    spurious_failures: bool,
+    space: Option<(Arc<Space>, Entered)>,
+}
+
+// What a run measured, beyond the results the threads report themselves
+#[derive(Default, Debug)]
+pub struct Report {
+    pub cache: Option<CacheProfile>,
+    pub cycles: Option<Cycles>,
+    pub log: Option<Vec<Executed>>,
+    // The virtual time the run took
+    pub time: Duration,
+}
+
+impl System {
+    pub fn new(model: MemoryModel) -> Self {
+        Self::with_seed(model, time_seed())
+    }
+
+    pub fn with_scheduler<S: Scheduler + 'static>(model: MemoryModel, scheduler: S) -> Self {
+        Self {
+            model,
+            scheduler: Box::new(scheduler),
+            caches: false,
+            costs: None,
+            seed: None,
+            coroutines: false,
+            log: false,
+            spurious_failures: true,
+            space: None,
+        }
+    }
+
+    // A uniform scheduler, seeded so a failing run can be reproduced
+    pub fn with_seed(model: MemoryModel, seed: u64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..Self::with_scheduler(model, UniformScheduler::new(seed))
         }
+    }
+
+    pub fn model(&self) -> MemoryModel {
+        self.model
+    }
+
+    // Tracks MESI states with a core per thread, and reports contention for each cache line
+    pub fn with_cache_profile(mut self) -> Self {
+        self.caches = true;
+        self
+    }
+
+    // Charges each operation's latency to the thread that issued it. Accesses cost what the
+    // cache layer says they would, so it's tracked too, but only reported if profiling.
+    pub fn with_cost_model(mut self, costs: CostModel) -> Self {
+        self.costs = Some(costs);
+        self
+    }
 
-        Some(ops.remove(ind))
+    // Runs the threads as coroutines on the calling thread, rather than spawning one for each
+    pub fn with_coroutines(mut self) -> Self {
+        self.coroutines = true;
+        self
     }
 
-    pub fn run<F: FnMut() + Send + 'static + ?Sized>(self, mut fns: Vec<Box<F>>) {
-        let s = std::time::UNIX_EPOCH.elapsed().unwrap().as_nanos() as u64;
-        let mut rng = ChaCha8Rng::seed_from_u64(s);
+    // Whether reservations can be lost without another thread writing, failing store exclusives.
+    // They can by default.
+    pub fn with_spurious_failures(mut self, enabled: bool) -> Self {
+        self.spurious_failures = enabled;
+        self
+    }
+
+    /*
+    Allocates from `space` while the system lives, rather than the process-wide space: the memory
+    the calling thread sets up for the run as well as what the run's threads allocate. The calling
+    thread goes back to the space it had when the system is dropped.
+    */
+    pub fn with_space(mut self, space: Arc<Space>) -> Self {
+        let entered = space.enter();
+        self.space = Some((space, entered));
+        self
+    }
+
+    // Records each operation as it executes, in the report
+    pub fn with_log(mut self) -> Self {
+        self.log = true;
+        self
+    }
+
+    // Operations that aren't blocked by an earlier operation in the queue, or waiting on the clock
+    pub fn available_ops(ops: &[Operation], now: Instant) -> Vec<usize> {
+        (0..ops.len())
+            .filter(|&ind| ops[ind].op.deadline().is_none_or(|d| d <= now))
+            .filter(|&ind| !(0..ind).any(|x| ops[x].blocks(&ops[ind])))
+            .collect()
+    }
+
+    pub fn get_op(&mut self, ops: &mut Vec<Operation>, now: Instant) -> Option<Operation> {
+        let available = Self::available_ops(ops, now);
+
+        if available.is_empty() {
+            return None;
+        }
+
+        let threads: Vec<usize> = available.iter().map(|&ind| ops[ind].thread).collect();
+        let choice = self.scheduler.choose(&threads);
+
+        Some(ops.remove(available[choice]))
+    }
 
-        //println!("Executing with Seed {}", s);
-        let mut handles = vec![];
-        let finished = Arc::new(AtomicUsize::new(0));
+    /*
+    Runs each closure as a thread of its own. If one panics, or an operation it queued does, no
+    further operations execute: the others are woken from anything they're waiting on and unwind,
+    and the first panic is returned.
+    */
+    pub fn run<F: FnMut() + Send + 'static + ?Sized>(
+        mut self,
+        mut fns: Vec<Box<F>>,
+    ) -> Result<Report, Panic> {
+        let panicked: Arc<Mutex<Option<Panic>>> = Default::default();
+        let seed = self.seed;
 
         let (sender, receiver) = channel();
+        let memories: Arc<Vec<Arc<ThreadMemory>>> =
+            Arc::new((0..fns.len()).map(|_| Default::default()).collect());
 
         let mut sys_info = SystemInfo {
             chan: sender,
             thread: 0,
-            parked: Arc::new(AtomicUsize::new(0)),
+            activity: Default::default(),
+            model: self.model,
+            spurious_failures: self.spurious_failures,
+            memory: Default::default(),
+            memories: memories.clone(),
+            global: Default::default(),
+            caches: (self.caches || self.costs.is_some()).then(Default::default),
+            clock: self.costs.map(|c| Arc::new(Clock::new(c, fns.len()))),
+            space: self.space.as_ref().map(|(s, _)| s.clone()),
+            time: Default::default(),
+        };
+
+        // Tasks all run on this thread, so the space is entered once for the whole run rather
+        // than by each of them, whose guards would drop in whatever order they finish
+        let coroutines = self.coroutines;
+        let _entered = sys_info
+            .space
+            .as_ref()
+            .filter(|_| coroutines)
+            .map(|s| s.enter());
+
+        let mut threads = if coroutines {
+            Threads::Tasks(Tasks::default())
+        } else {
+            Threads::Os(vec![])
         };
 
         for mut f in fns.drain(..) {
-            let finished = finished.clone();
+            let panicked = panicked.clone();
 
             sys_info.thread += 1;
+            sys_info.memory = memories[sys_info.thread - 1].clone();
             let sys_info = sys_info.clone();
 
-            handles.push(thread::spawn(move || {
+            threads.spawn(move || {
+                let (thread, activity) = (sys_info.thread, sys_info.activity.clone());
+                let _entered = sys_info
+                    .space
+                    .as_ref()
+                    .filter(|_| !coroutines)
+                    .map(|s| s.enter());
                 SYSTEM.with(|v| *v.lock().unwrap() = Some(sys_info));
-                f();
-                finished.fetch_add(1, SeqCst);
-            }));
+
+                if let Err(payload) = catch_unwind(AssertUnwindSafe(&mut f)) {
+                    if !payload.is::<TornDown>() {
+                        let panic = Panic {
+                            thread,
+                            payload,
+                            seed,
+                        };
+                        panicked.lock().unwrap().get_or_insert(panic);
+                    }
+                }
+
+                SYSTEM.with(|v| *v.lock().unwrap() = None);
+                activity.finish();
+            });
         }
 
         let mut operations = vec![];
+        let mut log = self.log.then(Vec::new);
+        let (activity, n) = (sys_info.activity.clone(), threads.len());
+        let time = sys_info.time.clone();
 
-        while finished.load(SeqCst) < handles.len() {
+        // Every thread queues its operations before parking, so once they're all idle the queue
+        // is complete. It's kept in thread order so decisions don't depend on OS thread timing.
+        let mut counts = threads.settle(&activity, |c| c.idle() == n);
+
+        loop {
             while let Ok(v) = receiver.try_recv() {
                 operations.push(v);
             }
 
-            let finished_count = finished.load(SeqCst);
-            let parked_count = sys_info.parked.load(SeqCst);
+            if panicked.lock().unwrap().is_some() {
+                operations.drain(..).for_each(|o| o.abandon());
+
+                if counts.finished == n {
+                    break;
+                }
+
+                // Threads still running may queue more before they next wait, or finish
+                counts = threads.settle(&activity, |c| c != counts);
+                continue;
+            }
+
+            operations.sort_by_key(|o| o.thread);
 
-            if finished_count + parked_count == handles.len() {
-                if let Some(o) = self.get_op(&mut operations, rng.next_u64() as usize) {
-                    o.execute();
+            // Schedulers can panic too, such as a replay that diverges from its trace
+            let chosen = catch_unwind(AssertUnwindSafe(|| {
+                self.get_op(&mut operations, time.now())
+            }));
+
+            let chosen = match chosen {
+                Ok(chosen) => chosen,
+                Err(payload) => {
+                    panicked.lock().unwrap().get_or_insert(Panic {
+                        thread: SCHEDULER,
+                        payload,
+                        seed,
+                    });
+                    continue;
+                }
+            };
+
+            match chosen {
+                Some(o) => {
+                    if let Some(log) = &mut log {
+                        log.push(Executed {
+                            thread: o.thread,
+                            category: o.op.category(),
+                            name: o.op.name(),
+                        });
+                    }
+
+                    if let Err(payload) = catch_unwind(AssertUnwindSafe(|| o.execute())) {
+                        o.abandon();
+                        panicked.lock().unwrap().get_or_insert(Panic {
+                            thread: o.thread,
+                            payload,
+                            seed,
+                        });
+                    }
+                }
+                None => {
+                    // Every thread is blocked, and what's left is waiting on the clock
+                    if let Some(next) = operations.iter().filter_map(|o| o.op.deadline()).min() {
+                        time.advance_to(next);
+                        continue;
+                    }
+
+                    // Operations nothing waited on still execute after their thread finishes
+                    if counts.finished == n {
+                        break;
+                    }
                 }
             }
+
+            counts = threads.settle(&activity, |c| c.idle() == n);
+        }
+
+        threads.join();
+
+        if let Some(panic) = panicked.lock().unwrap().take() {
+            return Err(panic);
+        }
+
+        Ok(Report {
+            cache: sys_info.caches.filter(|_| self.caches).map(|c| c.profile()),
+            cycles: sys_info.clock.map(|c| c.cycles()),
+            log,
+            time: time.now().since_start(),
+        })
+    }
+}
+
+enum Threads {
+    Os(Vec<JoinHandle<()>>),
+    Tasks(Tasks<()>),
+}
+
+impl Threads {
+    fn spawn<F: FnOnce() + Send + 'static>(&mut self, f: F) {
+        match self {
+            Threads::Os(handles) => handles.push(thread::spawn(f)),
+            Threads::Tasks(tasks) => tasks.push(Task::new(f)),
         }
+    }
 
-        for h in handles {
-            h.join().unwrap()
+    fn len(&self) -> usize {
+        match self {
+            Threads::Os(handles) => handles.len(),
+            Threads::Tasks(tasks) => tasks.len(),
+        }
+    }
+
+    // Lets the threads run until `until` holds. Tasks only run when resumed, and each runs until
+    // it waits or finishes, so resuming them all in turn is enough.
+    fn settle<F: Fn(Counts) -> bool>(&mut self, activity: &Activity, until: F) -> Counts {
+        if let Threads::Tasks(tasks) = self {
+            tasks.resume_all();
+        }
+
+        activity.wait_until(until)
+    }
+
+    fn join(self) {
+        if let Threads::Os(handles) = self {
+            for h in handles {
+                h.join().unwrap()
+            }
         }
     }
 }
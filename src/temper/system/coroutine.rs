@@ -0,0 +1,11 @@
+use crate::temper::system::core::SYSTEM;
+use sched::coroutine;
+
+pub use sched::coroutine::{active, Task, Tasks};
+
+// Tasks share the thread's locals, so the task's system info is put aside while others run
+pub fn suspend() {
+    let info = SYSTEM.with(|s| s.lock().unwrap().take());
+    coroutine::suspend();
+    SYSTEM.with(|s| *s.lock().unwrap() = info);
+}
@@ -0,0 +1,93 @@
+use crate::temper::memory::cache::Fetch;
+use std::sync::Mutex;
+
+// Latencies in cycles, roughly those of a recent x86 core
+#[derive(Copy, Clone, Debug)]
+pub struct CostModel {
+    pub l1_hit: usize,
+    pub memory: usize,
+    // Fetching a line from, or invalidating it in, another core's cache
+    pub remote_transfer: usize,
+    // Waiting for earlier accesses, on top of what they cost
+    pub fence: usize,
+    // The LOCK prefix on a read-modify-write, on top of the write itself
+    pub locked_rmw: usize,
+    // Each store a fence or locked read-modify-write has to drain from the store buffer
+    pub drain: usize,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        CostModel {
+            l1_hit: 4,
+            memory: 200,
+            remote_transfer: 70,
+            fence: 30,
+            locked_rmw: 20,
+            drain: 10,
+        }
+    }
+}
+
+impl CostModel {
+    pub fn access(&self, fetch: Fetch) -> usize {
+        match fetch {
+            Fetch::Hit => self.l1_hit,
+            Fetch::Memory => self.memory,
+            Fetch::Remote => self.remote_transfer,
+        }
+    }
+}
+
+// Virtual cycles each thread spent, and the operations it spent them on. Threads are numbered
+// from 1, so thread t is at index t - 1.
+#[derive(Clone, Default, Debug)]
+pub struct Cycles {
+    pub cycles: Vec<usize>,
+    pub ops: Vec<usize>,
+}
+
+impl Cycles {
+    pub fn total(&self) -> usize {
+        self.cycles.iter().sum()
+    }
+
+    pub fn per_op(&self) -> f64 {
+        self.total() as f64 / self.ops.iter().sum::<usize>().max(1) as f64
+    }
+
+    // The thread that finished last, were the threads running side by side
+    pub fn elapsed(&self) -> usize {
+        self.cycles.iter().copied().max().unwrap_or(0)
+    }
+}
+
+// Per-thread cycle counters, charged as operations execute
+pub struct Clock {
+    pub costs: CostModel,
+    counters: Mutex<Cycles>,
+}
+
+impl Clock {
+    pub fn new(costs: CostModel, threads: usize) -> Clock {
+        Clock {
+            costs,
+            counters: Mutex::new(Cycles {
+                cycles: vec![0; threads],
+                ops: vec![0; threads],
+            }),
+        }
+    }
+
+    pub fn charge(&self, thread: usize, cycles: usize) {
+        self.counters.lock().unwrap().cycles[thread - 1] += cycles;
+    }
+
+    pub fn count(&self, thread: usize) {
+        self.counters.lock().unwrap().ops[thread - 1] += 1;
+    }
+
+    pub fn cycles(&self) -> Cycles {
+        self.counters.lock().unwrap().clone()
+    }
+}
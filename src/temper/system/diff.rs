@@ -0,0 +1,137 @@
+use crate::temper::memory::address::Space;
+use crate::temper::memory::core::MemoryModel;
+use crate::temper::system::core::System;
+use crate::temper::system::parallel::{run_seeds, Outcomes};
+use std::collections::BTreeSet;
+use std::fmt::{Debug, Display, Formatter};
+use std::ops::Range;
+use std::sync::Arc;
+
+// One simulator's run of a program for a seed, such as temper under one of its models
+pub struct Column<'a, T> {
+    pub name: String,
+    run: Box<dyn Fn(u64) -> T + Sync + 'a>,
+}
+
+impl<'a, T> Column<'a, T> {
+    pub fn new<F: Fn(u64) -> T + Sync + 'a>(name: &str, run: F) -> Self {
+        Column {
+            name: name.to_string(),
+            run: Box::new(run),
+        }
+    }
+
+    /*
+    Runs `f` on a system for the model, seeded with the seed and with an address space of its own,
+    as the parallel runner does. `f` can branch on `System::model`, although the interesting diffs
+    come from programs that don't.
+    */
+    pub fn model<F: Fn(System) -> T + Sync>(model: MemoryModel, f: &'a F) -> Self {
+        Self::new(&format!("{:?}", model), move |seed| {
+            let space = Arc::new(Space::default());
+            f(System::with_seed(model, seed).with_space(space))
+        })
+    }
+}
+
+// The outcomes one program had in each column, over the same seeds
+pub struct ModelDiff<T: Ord> {
+    pub columns: Vec<(String, Outcomes<T>)>,
+}
+
+impl<T: Ord + Send> ModelDiff<T> {
+    pub fn new(columns: Vec<Column<T>>, seeds: Range<u64>, workers: usize) -> Self {
+        ModelDiff {
+            columns: columns
+                .into_iter()
+                .map(|c| (c.name, run_seeds(seeds.clone(), workers, c.run)))
+                .collect(),
+        }
+    }
+
+    // The program under each of temper's models
+    pub fn models<F: Fn(System) -> T + Sync>(
+        models: &[MemoryModel],
+        seeds: Range<u64>,
+        workers: usize,
+        f: F,
+    ) -> Self {
+        let columns = models.iter().map(|&m| Column::model(m, &f)).collect();
+        Self::new(columns, seeds, workers)
+    }
+}
+
+impl<T: Ord> ModelDiff<T> {
+    pub fn outcomes(&self, name: &str) -> Option<&Outcomes<T>> {
+        self.columns.iter().find(|(n, _)| n == name).map(|(_, o)| o)
+    }
+
+    pub fn results(&self) -> BTreeSet<&T> {
+        self.columns.iter().flat_map(|(_, o)| o.results()).collect()
+    }
+
+    // Results seen in some columns but not others
+    pub fn differences(&self) -> BTreeSet<&T> {
+        self.results()
+            .into_iter()
+            .filter(|r| !self.columns.iter().all(|(_, o)| o.outcomes.contains_key(r)))
+            .collect()
+    }
+}
+
+// A row per result and a column per simulator, counting the runs that had it
+impl<T: Ord + Debug> Display for ModelDiff<T> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let rows: Vec<(String, Vec<String>)> = self
+            .results()
+            .into_iter()
+            .map(|r| {
+                let cells = self
+                    .columns
+                    .iter()
+                    .map(|(_, o)| {
+                        o.outcomes
+                            .get(r)
+                            .map_or("-".to_string(), |o| o.count.to_string())
+                    })
+                    .collect();
+                (format!("{:?}", r), cells)
+            })
+            .collect();
+
+        let headers: Vec<&String> = self.columns.iter().map(|(n, _)| n).collect();
+        let first = rows
+            .iter()
+            .map(|(r, _)| r.len())
+            .chain(["outcome".len()])
+            .max()
+            .unwrap();
+        let widths: Vec<usize> = headers
+            .iter()
+            .enumerate()
+            .map(|(i, h)| {
+                rows.iter()
+                    .map(|(_, c)| c[i].len())
+                    .chain([h.len()])
+                    .max()
+                    .unwrap()
+            })
+            .collect();
+
+        write!(f, "{:first$}", "outcome")?;
+        for (h, w) in headers.iter().zip(widths.iter()) {
+            write!(f, "  {:>w$}", h)?;
+        }
+        writeln!(f)?;
+
+        for (r, cells) in rows {
+            write!(f, "{:first$}", r)?;
+            for (c, w) in cells.iter().zip(widths.iter()) {
+                write!(f, "  {:>w$}", c)?;
+            }
+            writeln!(f)?;
+        }
+
+        Ok(())
+    }
+}
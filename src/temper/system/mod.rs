@@ -1 +1,7 @@
 pub mod core;
+pub mod coroutine;
+pub mod cost;
+pub mod diff;
+pub mod parallel;
+pub mod result;
+pub mod time;
@@ -0,0 +1,127 @@
+use crate::temper::memory::address::Space;
+use crate::temper::memory::core::MemoryModel;
+use crate::temper::system::core::System;
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::panic::resume_unwind;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub struct Outcome {
+    pub count: usize,
+    // The lowest seed that produced it, to reproduce it with
+    pub seed: u64,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Outcomes<T: Ord> {
+    pub outcomes: BTreeMap<T, Outcome>,
+}
+
+impl<T: Ord> Default for Outcomes<T> {
+    fn default() -> Self {
+        Outcomes {
+            outcomes: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: Ord> Outcomes<T> {
+    pub fn add(&mut self, result: T, seed: u64) {
+        self.merge_one(result, Outcome { count: 1, seed });
+    }
+
+    pub fn merge(&mut self, other: Outcomes<T>) {
+        for (result, outcome) in other.outcomes {
+            self.merge_one(result, outcome);
+        }
+    }
+
+    fn merge_one(&mut self, result: T, outcome: Outcome) {
+        let entry = self.outcomes.entry(result).or_insert(Outcome {
+            count: 0,
+            seed: outcome.seed,
+        });
+
+        entry.count += outcome.count;
+        entry.seed = entry.seed.min(outcome.seed);
+    }
+
+    pub fn results(&self) -> impl Iterator<Item = &T> {
+        self.outcomes.keys()
+    }
+
+    pub fn runs(&self) -> usize {
+        self.outcomes.values().map(|o| o.count).sum()
+    }
+}
+
+/*
+Runs a simulation for every seed in a range, spread across worker threads. `f` builds and runs a
+program on the system it's given, seeded with the seed, and returns its outcome. Each system also
+has an address space of its own, so what it does doesn't depend on the worker it lands on or the
+seeds before it, and the outcomes are the same for any number of workers.
+*/
+pub struct ParallelRunner {
+    workers: usize,
+    model: MemoryModel,
+}
+
+impl ParallelRunner {
+    pub fn new(model: MemoryModel, workers: usize) -> Self {
+        assert!(workers >= 1);
+
+        ParallelRunner { workers, model }
+    }
+
+    pub fn run<T: Ord + Send, F: Fn(System) -> T + Sync>(
+        &self,
+        seeds: Range<u64>,
+        f: F,
+    ) -> Outcomes<T> {
+        run_seeds(seeds, self.workers, |seed| {
+            let space = Arc::new(Space::default());
+            f(System::with_seed(self.model, seed).with_space(space))
+        })
+    }
+}
+
+// Runs `f` for every seed in a range, spread across worker threads, for simulators that don't
+// run on a `System`. A seed has to determine what `f` returns for the outcomes to be repeatable.
+pub fn run_seeds<T: Ord + Send, F: Fn(u64) -> T + Sync>(
+    seeds: Range<u64>,
+    workers: usize,
+    f: F,
+) -> Outcomes<T> {
+    assert!(workers >= 1);
+
+    let next = AtomicU64::new(seeds.start);
+
+    let worker = || {
+        let mut outcomes = Outcomes::default();
+
+        loop {
+            let seed = next.fetch_add(1, Ordering::SeqCst);
+
+            if seed >= seeds.end {
+                break;
+            }
+
+            outcomes.add(f(seed), seed);
+        }
+
+        outcomes
+    };
+
+    thread::scope(|scope| {
+        let handles: Vec<_> = (0..workers).map(|_| scope.spawn(worker)).collect();
+
+        let mut outcomes = Outcomes::default();
+        for h in handles {
+            outcomes.merge(h.join().unwrap_or_else(|e| resume_unwind(e)));
+        }
+        outcomes
+    })
+}
@@ -0,0 +1,121 @@
+use crate::temper::system::core::{with_system, Activity, TornDown};
+use crate::temper::utils::sleepwait::SleepWait;
+use std::ops::Deref;
+use std::panic::resume_unwind;
+use std::sync::{Arc, Mutex, OnceLock};
+
+struct ResultSlot<T> {
+    value: Option<T>,
+    waiting: bool,
+}
+
+// The result of a queued operation. The thread parks when it waits on one that hasn't executed.
+pub struct PendingResult<T> {
+    result: Arc<Mutex<ResultSlot<T>>>,
+    value: OnceLock<T>,
+    sleep_wait: Arc<SleepWait>,
+    activity: Arc<Activity>,
+}
+
+// The operation's end of a result, which it completes when it executes
+pub struct Completion<T> {
+    result: Arc<Mutex<ResultSlot<T>>>,
+    sleep_wait: Arc<SleepWait>,
+    activity: Arc<Activity>,
+}
+
+// A result for an operation the calling thread is about to queue
+pub fn channel<T>() -> (Completion<T>, PendingResult<T>) {
+    let result = Arc::new(Mutex::new(ResultSlot {
+        value: None,
+        waiting: false,
+    }));
+    let sleep_wait = Arc::new(SleepWait::default());
+    let activity = with_system(|s| s.activity.clone());
+
+    (
+        Completion {
+            result: result.clone(),
+            sleep_wait: sleep_wait.clone(),
+            activity: activity.clone(),
+        },
+        PendingResult {
+            result,
+            value: OnceLock::new(),
+            sleep_wait,
+            activity,
+        },
+    )
+}
+
+impl<T> Completion<T> {
+    pub fn complete(&self, value: T) {
+        let mut slot = self.result.lock().unwrap();
+        slot.value = Some(value);
+
+        if slot.waiting {
+            self.activity.unpark();
+        }
+
+        self.sleep_wait.signal();
+    }
+
+    // Wakes the thread without a value, when the operation is abandoned
+    pub fn abandon(&self) {
+        self.sleep_wait.signal();
+    }
+
+    pub fn waiter(&self) -> Arc<SleepWait> {
+        self.sleep_wait.clone()
+    }
+}
+
+impl<T> Deref for PendingResult<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        self.value.get_or_init(|| {
+            let mut slot = self.result.lock().unwrap();
+
+            // The thread is unparked by the operation that fills the slot, not when it wakes up,
+            // so the scheduler never sees it as parked while it's actually running
+            if slot.value.is_none() {
+                slot.waiting = true;
+                self.activity.park();
+                drop(slot);
+
+                self.sleep_wait.wait();
+                slot = self.result.lock().unwrap();
+            }
+
+            // Woken without a value, because another thread panicked and the run is over
+            match slot.value.take() {
+                Some(v) => v,
+                None => {
+                    drop(slot);
+                    resume_unwind(Box::new(TornDown))
+                }
+            }
+        })
+    }
+}
+
+impl<T> PendingResult<T> {
+    pub fn wait(self) -> T {
+        let _ = &*self;
+        self.value.into_inner().unwrap()
+    }
+
+    // A result the thread already has, such as one it waited on to build a larger operation
+    pub fn ready(value: T) -> Self {
+        PendingResult {
+            result: Arc::new(Mutex::new(ResultSlot {
+                value: None,
+                waiting: false,
+            })),
+            value: OnceLock::from(value),
+            sleep_wait: Default::default(),
+            activity: Default::default(),
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::temper::system::core::{submit, with_system, Category, Op};
+use crate::temper::system::result::{channel, Completion, PendingResult};
+use std::any::Any;
+use std::ops::{Add, Sub};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// How often `timeout` checks its condition
+pub const POLL_INTERVAL: Duration = Duration::from_millis(1);
+
+// A point in virtual time, measured from the start of the run
+#[derive(Copy, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+pub struct Instant(Duration);
+
+impl Instant {
+    pub fn now() -> Instant {
+        with_system(|s| s.time.now())
+    }
+
+    pub fn since_start(&self) -> Duration {
+        self.0
+    }
+
+    pub fn duration_since(&self, earlier: Instant) -> Duration {
+        self.0.saturating_sub(earlier.0)
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        Instant::now().duration_since(*self)
+    }
+}
+
+impl Add<Duration> for Instant {
+    type Output = Instant;
+
+    fn add(self, rhs: Duration) -> Instant {
+        Instant(self.0 + rhs)
+    }
+}
+
+impl Sub<Instant> for Instant {
+    type Output = Duration;
+
+    fn sub(self, rhs: Instant) -> Duration {
+        self.duration_since(rhs)
+    }
+}
+
+/*
+The run's virtual clock. It only moves when every thread is blocked and the only operations left
+are waiting on it, and then jumps straight to the earliest deadline, so sleeping costs nothing in
+real time and a seed always sees the same times.
+*/
+#[derive(Default)]
+pub struct VirtualTime {
+    now: Mutex<Instant>,
+}
+
+impl VirtualTime {
+    pub fn now(&self) -> Instant {
+        *self.now.lock().unwrap()
+    }
+
+    pub fn advance_to(&self, instant: Instant) {
+        let mut now = self.now.lock().unwrap();
+        *now = (*now).max(instant);
+    }
+}
+
+// Fires at its deadline, handing back the time it fired at
+struct TimerOp {
+    deadline: Instant,
+    time: Arc<VirtualTime>,
+    completion: Completion<Instant>,
+}
+
+impl Op for TimerOp {
+    fn blocks(&self, _other: &(dyn Op + Send)) -> bool {
+        false
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn execute(&self) {
+        self.completion.complete(self.time.now());
+    }
+
+    fn abandon(&self) {
+        self.completion.abandon();
+    }
+
+    fn category(&self) -> Category {
+        Category::Time
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        Some(self.deadline)
+    }
+
+    fn name(&self) -> String {
+        format!("Timer {:?}", self.deadline.0)
+    }
+}
+
+// A timer that fires once `duration` has passed. The thread carries on until it waits on it.
+pub fn timer(duration: Duration) -> PendingResult<Instant> {
+    let (completion, result) = channel();
+    let time = with_system(|s| s.time.clone());
+
+    submit(TimerOp {
+        deadline: time.now() + duration,
+        time,
+        completion,
+    });
+
+    result
+}
+
+pub fn sleep(duration: Duration) {
+    timer(duration).wait();
+}
+
+// Checks `f` until it returns a value, or `duration` passes without one
+pub fn timeout<T, F: FnMut() -> Option<T>>(duration: Duration, mut f: F) -> Option<T> {
+    let deadline = Instant::now() + duration;
+
+    loop {
+        if let Some(v) = f() {
+            return Some(v);
+        }
+
+        let now = Instant::now();
+        if now >= deadline {
+            return None;
+        }
+
+        sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::temper::system::coroutine;
 use std::sync::{Condvar, Mutex};
 
 #[derive(Default)]
@@ -14,12 +15,24 @@ impl SleepWait {
         self.signal.notify_all();
     }
     pub fn wait(&self) {
+        // A coroutine can't block the thread it shares with the scheduler, so it yields instead
+        if coroutine::active() {
+            while !self.is_ready() {
+                coroutine::suspend();
+            }
+            return;
+        }
+
         let mut ready = self.ready.lock().unwrap();
 
         while !*ready {
             ready = self.signal.wait(ready).unwrap();
         }
     }
+
+    pub fn is_ready(&self) -> bool {
+        *self.ready.lock().unwrap()
+    }
 }
 
 #[cfg(test)]
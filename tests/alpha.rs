@@ -0,0 +1,79 @@
+mod common;
+
+use common::utils::{run_until, Test};
+
+use temper::temper::memory::barrier::Barrier;
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+
+/* Pointer publishing
+
+Thread 1:
+arr[1] = 42
+wmb
+p = 1
+
+Thread 2:
+i = p
+print(i)
+print(arr[i])
+
+The load of arr[i] depends on the load of p, which orders them everywhere but Alpha. There the
+reader's cache can still hold a stale arr[1], so (1, 0) is possible unless the reader issues an
+mb between the loads, as Linux's smp_read_barrier_depends did.
+*/
+
+fn publish(model: MemoryModel, read_barrier: bool) -> Vec<usize> {
+    let s = System::new(model);
+
+    let test = Test::default();
+
+    let writer = {
+        let test = test.clone();
+        move || {
+            test.arr.set(1, 42);
+            Atomic::<()>::barrier(Barrier::WMB);
+            test.a.set(1);
+        }
+    };
+
+    let reader = {
+        let test = test.clone();
+        move || {
+            let i = *test.a.get();
+            if read_barrier {
+                Atomic::<()>::barrier(Barrier::MB);
+            }
+            let v = *test.arr.get(i);
+            test.report_result(0, i);
+            test.report_result(1, v);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(writer), Box::new(reader)];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_dependent_loads() {
+    let ordered = vec![vec![0, 0], vec![1, 42]];
+
+    assert!(run_until(
+        || publish(MemoryModel::Alpha, false),
+        vec![vec![0, 0], vec![1, 0], vec![1, 42]],
+    ));
+    assert!(run_until(
+        || publish(MemoryModel::Alpha, true),
+        ordered.clone()
+    ));
+
+    assert!(run_until(
+        || publish(MemoryModel::ARMv8, false),
+        ordered.clone()
+    ));
+    assert!(run_until(|| publish(MemoryModel::POWER, false), ordered));
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use common::litmus::{message_passing, store_buffering, Ordering};
+use common::utils::run_until;
+
+use temper::temper::memory::barrier::Barrier;
+use temper::temper::memory::core::MemoryModel;
+use temper::temper::system::core::System;
+
+#[test]
+fn test_message_passing() {
+    let all = vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]];
+    let ordered = vec![vec![0, 0], vec![0, 1], vec![1, 1]];
+
+    for model in [MemoryModel::ARMv8, MemoryModel::RVWMO] {
+        assert!(run_until(
+            || message_passing(System::new(model), Ordering::Relaxed),
+            all.clone()
+        ));
+
+        assert!(run_until(
+            || message_passing(System::new(model), Ordering::AcquireRelease),
+            ordered.clone()
+        ));
+    }
+
+    assert!(run_until(
+        || message_passing(
+            System::new(MemoryModel::ARMv8),
+            Ordering::Barriers(Some(Barrier::DMB_ISHST), Some(Barrier::DMB_ISHLD))
+        ),
+        ordered.clone()
+    ));
+
+    assert!(run_until(
+        || message_passing(
+            System::new(MemoryModel::RVWMO),
+            Ordering::Barriers(Some(Barrier::FENCE_W_W), Some(Barrier::FENCE_R_R))
+        ),
+        ordered.clone()
+    ));
+
+    // Ordering the writer alone isn't enough
+    assert!(run_until(
+        || message_passing(
+            System::new(MemoryModel::ARMv8),
+            Ordering::Barriers(Some(Barrier::DMB_ISHST), None)
+        ),
+        all.clone()
+    ));
+
+    // Nor is a barrier of the wrong kind on the reader
+    assert!(run_until(
+        || message_passing(
+            System::new(MemoryModel::ARMv8),
+            Ordering::Barriers(Some(Barrier::DMB_ISHST), Some(Barrier::DMB_ISHST))
+        ),
+        all
+    ));
+}
+
+#[test]
+fn test_store_buffering() {
+    assert!(run_until(
+        || store_buffering(System::new(MemoryModel::ARMv8), Some(Barrier::DMB_ISHST)),
+        vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]],
+    ));
+
+    assert!(run_until(
+        || store_buffering(System::new(MemoryModel::ARMv8), Some(Barrier::DMB_ISH)),
+        vec![vec![0, 1], vec![1, 0], vec![1, 1]],
+    ));
+}
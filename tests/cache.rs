@@ -0,0 +1,111 @@
+mod common;
+
+use common::utils::{run_pair, run_until, Test};
+
+use std::sync::Arc;
+use temper::temper::memory::cache::{CacheProfile, LineStats};
+use temper::temper::memory::core::{MemoryModel, SharedMemory};
+use temper::temper::system::core::System;
+
+// Two threads each incrementing their own counter. Unpadded, the counters share a cache line.
+fn counters(padded: bool) -> CacheProfile {
+    let s = System::new(MemoryModel::Intel).with_cache_profile();
+
+    let test = Test::default();
+    let counters = Arc::new(if padded {
+        SharedMemory::<usize>::padded(2)
+    } else {
+        SharedMemory::<usize>::new(2)
+    });
+
+    let report = run_pair(s, |index| {
+        let test = test.clone();
+        let counters = counters.clone();
+        move || {
+            for _ in 0..3 {
+                let v = *counters.get(index);
+                counters.set(index, v + 1);
+            }
+            test.report_result(index, *counters.get(index));
+        }
+    });
+
+    assert_eq!(*test.results.lock().unwrap(), vec![3, 3]);
+    report.cache.unwrap()
+}
+
+#[test]
+fn test_false_sharing() {
+    // Each counter is read into Exclusive, then silently upgraded, whatever the interleaving
+    assert!(run_until(
+        || counters(true).total(),
+        vec![LineStats::default()]
+    ));
+
+    for _ in 0..100 {
+        let unpadded = counters(false);
+        assert_eq!(unpadded.lines.len(), 1);
+        assert!(unpadded.total().invalidations >= 1);
+        assert!(unpadded.total().transfers >= 1);
+    }
+}
+
+#[test]
+fn test_no_profile_by_default() {
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(|| {})];
+    assert!(System::new(MemoryModel::Intel)
+        .run(fns)
+        .unwrap()
+        .cache
+        .is_none());
+}
+
+// Per-thread statistics, two counters each, laid out one after another. Threads are numbered
+// from 1. With `shared` every thread updates the same counters instead.
+fn stats(padded: bool, shared: bool) -> (CacheProfile, Vec<usize>) {
+    let s = System::new(MemoryModel::Intel).with_cache_profile();
+
+    let stats = Arc::new(if padded {
+        SharedMemory::<usize>::padded(4)
+    } else {
+        SharedMemory::<usize>::new(4)
+    });
+    let addresses = (0..4).map(|i| stats.address(i)).collect();
+
+    let report = run_pair(s, |index| {
+        let stats = stats.clone();
+        let base = if shared { 0 } else { index * 2 };
+        move || {
+            for i in base..base + 2 {
+                let v = *stats.get(i);
+                stats.set(i, v + 1);
+            }
+        }
+    });
+
+    (report.cache.unwrap(), addresses)
+}
+
+#[test]
+fn test_false_sharing_report() {
+    let (profile, addresses) = stats(false, false);
+    let report = profile.false_sharing();
+
+    assert_eq!(report.len(), 1);
+    let threads: Vec<Vec<usize>> = report[0]
+        .threads
+        .values()
+        .map(|a| a.iter().copied().collect())
+        .collect();
+    assert_eq!(
+        threads,
+        vec![addresses[0..2].to_vec(), addresses[2..4].to_vec()]
+    );
+    assert!(report[0].stats.transfers >= 1);
+    assert!(report[0].to_string().contains("pad thread 1's +0 +8"));
+    assert!(report[0].to_string().contains("pad thread 2's +16 +24"));
+
+    // Padding fixes it, and threads sharing the counters themselves is true sharing
+    assert!(stats(true, false).0.false_sharing().is_empty());
+    assert!(stats(false, true).0.false_sharing().is_empty());
+}
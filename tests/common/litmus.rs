@@ -0,0 +1,110 @@
+use crate::common::utils::{run_pair, Test};
+
+use temper::temper::memory::barrier::Barrier;
+use temper::temper::memory::core::Atomic;
+use temper::temper::system::core::System;
+
+/* Message passing
+
+Thread 1:
+data = 1
+flag = 1
+
+Thread 2:
+print(flag)
+print(data)
+
+On ARMv8 and RISC-V both pairs of accesses can be reordered, so (1, 0) is possible. It takes
+ordering on both sides to rule it out: a store barrier or release on the writer, and a load
+barrier or acquire on the reader.
+*/
+
+#[derive(Copy, Clone)]
+#[allow(unused)]
+pub enum Ordering {
+    Relaxed,
+    Barriers(Option<Barrier>, Option<Barrier>),
+    AcquireRelease,
+}
+
+#[allow(unused)]
+pub fn message_passing(s: System, ordering: Ordering) -> Vec<usize> {
+    let test = Test::default();
+
+    let writer = {
+        let test = test.clone();
+        move || {
+            test.a.set(1);
+            match ordering {
+                Ordering::Barriers(Some(b), _) => Atomic::<()>::barrier(b),
+                Ordering::AcquireRelease => {
+                    test.b.set_release(1);
+                    return;
+                }
+                _ => {}
+            }
+            test.b.set(1);
+        }
+    };
+
+    let reader = {
+        let test = test.clone();
+        move || {
+            let flag = match ordering {
+                Ordering::AcquireRelease => test.b.get_acquire(),
+                _ => test.b.get(),
+            };
+            if let Ordering::Barriers(_, Some(b)) = ordering {
+                Atomic::<()>::barrier(b)
+            }
+            let data = test.a.get();
+            test.report_result(0, *flag);
+            test.report_result(1, *data);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(writer), Box::new(reader)];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+/* Store buffering
+
+Thread 1:
+a = 1
+print(b)
+
+Thread 2:
+b = 1
+print(a)
+
+Only a barrier ordering earlier stores before later loads rules out (0, 0).
+*/
+
+#[allow(unused)]
+pub fn store_buffering(s: System, barrier: Option<Barrier>) -> Vec<usize> {
+    let test = Test::default();
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        move || {
+            let (mine, theirs) = if index == 0 {
+                (&test.a, &test.b)
+            } else {
+                (&test.b, &test.a)
+            };
+            mine.set(1);
+            if let Some(b) = barrier {
+                Atomic::<()>::barrier(b)
+            }
+            let res = *theirs.get();
+            test.report_result(index, res);
+        }
+    });
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
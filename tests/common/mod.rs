@@ -1 +1,2 @@
+pub mod litmus;
 pub mod utils;
@@ -3,6 +3,7 @@ use std::fmt::Debug;
 use std::hash::Hash;
 use std::sync::{Arc, Mutex};
 use temper::temper::memory::core::{Atomic, SharedMemory};
+use temper::temper::system::core::{Report, System};
 
 /* Default test environment provides for four variables */
 
@@ -33,6 +34,7 @@ impl Default for Test {
 }
 
 impl Test {
+    #[allow(unused)]
     pub fn report_result(&self, index: usize, result: usize) {
         let mut res = self.results.lock().unwrap();
         while res.len() <= index {
@@ -42,6 +44,7 @@ impl Test {
     }
 }
 
+#[allow(unused)]
 fn check_set<T: Clone + Eq + Hash>(hs: &HashSet<T>, arr: &Vec<T>) -> bool {
     let mut ns = HashSet::new();
     for x in arr {
@@ -50,6 +53,7 @@ fn check_set<T: Clone + Eq + Hash>(hs: &HashSet<T>, arr: &Vec<T>) -> bool {
     ns == *hs
 }
 
+#[allow(unused)]
 pub fn run_until<T: Clone + Eq + Hash + Debug, F: FnMut() -> T>(
     mut f: F,
     expected: Vec<T>,
@@ -72,3 +76,10 @@ pub fn run_until<T: Clone + Eq + Hash + Debug, F: FnMut() -> T>(
     println!("Failed {:?} {:?}", res, expected);
     false
 }
+
+// Runs a thread for each of index 0 and 1, built by `thread`
+#[allow(unused)]
+pub fn run_pair<F: FnMut() + Send + 'static, T: Fn(usize) -> F>(s: System, thread: T) -> Report {
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(thread(0)), Box::new(thread(1))];
+    s.run(fns).unwrap()
+}
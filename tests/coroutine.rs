@@ -0,0 +1,52 @@
+mod common;
+
+use common::litmus::store_buffering;
+use common::utils::{run_until, Test};
+
+use temper::temper::memory::core::MemoryModel;
+use temper::temper::system::core::System;
+
+#[test]
+fn test_coroutines() {
+    let all = vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]];
+
+    for model in [MemoryModel::TSO, MemoryModel::ARMv8, MemoryModel::POWER] {
+        assert!(run_until(
+            || store_buffering(System::new(model).with_coroutines(), None),
+            all.clone()
+        ));
+    }
+}
+
+#[test]
+fn test_backends_agree() {
+    // Threads queue the same operations either way, so a seed picks the same execution
+    for model in [MemoryModel::Intel, MemoryModel::TSO, MemoryModel::POWER] {
+        for seed in 0..50 {
+            assert_eq!(
+                store_buffering(System::with_seed(model, seed), None),
+                store_buffering(System::with_seed(model, seed).with_coroutines(), None)
+            );
+        }
+    }
+}
+
+#[test]
+fn test_coroutine_panic() {
+    let test = Test::default();
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new({
+            let test = test.clone();
+            move || assert_eq!(*test.a.get(), 1)
+        }),
+        Box::new(move || while *test.a.get() == 0 {}),
+    ];
+
+    let panic = System::with_seed(MemoryModel::Intel, 7)
+        .with_coroutines()
+        .run(fns)
+        .unwrap_err();
+    assert_eq!(panic.thread, 1);
+    assert_eq!(panic.seed, Some(7));
+}
@@ -0,0 +1,122 @@
+use sched::scheduler::Scheduler;
+use std::sync::Arc;
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+use temper::temper::system::cost::{CostModel, Cycles};
+
+fn single<F: Fn(&Atomic<usize>) + Send + 'static>(s: System, f: F) -> Cycles {
+    let a = Arc::new(Atomic::new(0));
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(move || f(&a))];
+
+    let report = s.with_cost_model(CostModel::default()).run(fns).unwrap();
+
+    assert!(report.cache.is_none());
+    report.cycles.unwrap()
+}
+
+#[test]
+fn test_uncontended_costs() {
+    let costs = CostModel::default();
+
+    // The first read misses to memory, and the write upgrades the Exclusive line silently
+    let cycles = single(System::new(MemoryModel::Intel), |a| {
+        let v = *a.get();
+        a.set(v + 1);
+    });
+    assert_eq!(cycles.cycles, vec![costs.memory + costs.l1_hit]);
+    assert_eq!(cycles.ops, vec![2]);
+
+    let cycles = single(System::new(MemoryModel::Intel), |a| {
+        a.set(1);
+        Atomic::<usize>::fence();
+        a.fetch_add(1);
+    });
+    assert_eq!(
+        cycles.total(),
+        costs.memory + costs.fence + costs.locked_rmw + costs.l1_hit
+    );
+    assert_eq!(cycles.ops, vec![3]);
+}
+
+// Runs the newest operation it can, so no store is flushed until something drains it
+struct Newest;
+
+impl Scheduler for Newest {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        runnable.len() - 1
+    }
+}
+
+// Stores `stores` times, then fences, on TSO
+fn drained(stores: usize, fence: bool) -> usize {
+    single(System::with_scheduler(MemoryModel::TSO, Newest), move |a| {
+        for i in 0..stores {
+            a.set(i);
+        }
+        if fence {
+            Atomic::<usize>::fence();
+        }
+    })
+    .total()
+}
+
+#[test]
+fn test_fence_costs() {
+    let costs = CostModel::default();
+
+    // The stores cost the same either way, but the fence pays to drain each one
+    for stores in 0..4 {
+        assert_eq!(
+            drained(stores, true) - drained(stores, false),
+            costs.fence + stores * costs.drain
+        );
+    }
+}
+
+// Each thread increments the same counter
+fn contended(threads: usize, increments: usize) -> Cycles {
+    let counter = Arc::new(Atomic::new(0));
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = (0..threads)
+        .map(|_| {
+            let counter = counter.clone();
+            Box::new(move || {
+                for _ in 0..increments {
+                    counter.fetch_add(1);
+                }
+            }) as Box<dyn FnMut() + Send>
+        })
+        .collect();
+
+    System::new(MemoryModel::Intel)
+        .with_cost_model(CostModel::default())
+        .run(fns)
+        .unwrap()
+        .cycles
+        .unwrap()
+}
+
+#[test]
+fn test_contention_costs() {
+    let alone = contended(1, 20);
+    let shared = contended(4, 20);
+
+    assert_eq!(shared.ops, vec![20; 4]);
+    assert!(shared.per_op() > alone.per_op());
+
+    // Every increment pays at least an L1 hit and the lock, and at most a transfer and the lock
+    let costs = CostModel::default();
+    assert!(shared.per_op() >= (costs.l1_hit + costs.locked_rmw) as f64);
+    assert!(shared.per_op() <= (costs.memory + costs.locked_rmw) as f64);
+}
+
+#[test]
+fn test_no_cycles_by_default() {
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(|| {})];
+    assert!(System::new(MemoryModel::Intel)
+        .run(fns)
+        .unwrap()
+        .cycles
+        .is_none());
+}
@@ -0,0 +1,209 @@
+mod common;
+
+use common::utils::{run_pair, run_until, Test};
+
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+
+/* Reservation granularity
+
+Thread 1:
+i = load_exclusive(arr[0])
+print(store_exclusive(arr[0], i + 1))
+
+Thread 2:
+arr[k] = 1
+
+A write anywhere in the reserved cache line clears the reservation, so the store exclusive can
+fail when k is 1. Eight elements along it's on another line, and always succeeds.
+*/
+
+fn granularity(k: usize) -> Vec<usize> {
+    let s = System::new(MemoryModel::ARMv8).with_spurious_failures(false);
+
+    let test = Test::default();
+
+    let t1 = {
+        let test = test.clone();
+        move || {
+            let i = *test.arr.load_exclusive(0);
+            let stored = *test.arr.store_exclusive(0, i + 1);
+            test.report_result(0, stored as usize);
+        }
+    };
+
+    let t2 = {
+        let test = test.clone();
+        move || {
+            test.arr.set(k, 1);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(t1), Box::new(t2)];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_reservation_granularity() {
+    assert!(run_until(|| granularity(0), vec![vec![0], vec![1]]));
+    assert!(run_until(|| granularity(1), vec![vec![0], vec![1]]));
+    assert!(run_until(|| granularity(8), vec![vec![1]]));
+}
+
+fn uncontended(spurious: bool) -> Vec<usize> {
+    let s = System::new(MemoryModel::ARMv8).with_spurious_failures(spurious);
+
+    let test = Test::default();
+
+    let t1 = {
+        let test = test.clone();
+        move || {
+            let i = *test.a.load_exclusive();
+            let stored = *test.a.store_exclusive(i + 1);
+            test.report_result(0, stored as usize);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(t1)];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_spurious_failure() {
+    assert!(run_until(|| uncontended(true), vec![vec![0], vec![1]]));
+    assert!(run_until(|| uncontended(false), vec![vec![1]]));
+}
+
+// Threads retrying a contended LL/SC increment until it succeeds. Every failure is a lost race,
+// so with spurious failures off the two threads fail at most once between them.
+fn contended() -> Vec<usize> {
+    let s = System::new(MemoryModel::ARMv8).with_spurious_failures(false);
+
+    let test = Test::default();
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        move || {
+            let mut failures = 0;
+
+            loop {
+                let i = *test.a.load_exclusive();
+                if *test.a.store_exclusive(i + 1) {
+                    break;
+                }
+                failures += 1;
+            }
+
+            test.report_result(index, failures);
+        }
+    });
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_contention() {
+    assert!(run_until(
+        contended,
+        vec![vec![0, 0], vec![0, 1], vec![1, 0]]
+    ));
+}
+
+/* A store exclusive after a buffered store, under TSO
+
+a = 1
+i = load_exclusive(a)
+print(store_exclusive(a, i + 1))
+fence
+print(a)
+
+The store exclusive writes straight to memory, so the buffered store has to go out first rather
+than land on top of it later.
+*/
+
+fn buffered_then_exclusive() -> Vec<usize> {
+    let s = System::new(MemoryModel::TSO).with_spurious_failures(false);
+
+    let test = Test::default();
+
+    let t1 = {
+        let test = test.clone();
+        move || {
+            test.a.set(1);
+            let i = *test.a.load_exclusive();
+            let stored = *test.a.store_exclusive(i + 1);
+            Atomic::<()>::fence();
+            test.report_result(0, stored as usize);
+            test.report_result(1, *test.a.get());
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(t1)];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+/* Buffered stores and locked read-modify-writes clear reservations, under TSO
+
+Thread 1:
+i = load_exclusive(a)
+print(i, store_exclusive(a, i + 1))
+
+Thread 2:
+a = 5
+print(fetch_add(a, 0))
+
+If thread 1 read 0 and its store exclusive succeeded, the 5 reached memory after it, so the
+fetch_add sees 5. (0, 1, 1) would mean the store exclusive went through with the 5 in between.
+*/
+
+fn tso_reservation() -> Vec<usize> {
+    let s = System::new(MemoryModel::TSO).with_spurious_failures(false);
+
+    let test = Test::default();
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        move || {
+            if index == 0 {
+                let i = *test.a.load_exclusive();
+                let stored = *test.a.store_exclusive(i + 1);
+                test.report_result(0, i);
+                test.report_result(1, stored as usize);
+            } else {
+                test.a.set(5);
+                test.report_result(2, *test.a.fetch_add(0));
+            }
+        }
+    });
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_tso_exclusive() {
+    assert!(run_until(buffered_then_exclusive, vec![vec![1, 2]]));
+    assert!(run_until(
+        tso_reservation,
+        vec![
+            vec![0, 0, 5],
+            vec![0, 1, 5],
+            vec![5, 0, 5],
+            vec![5, 1, 5],
+            vec![5, 1, 6]
+        ]
+    ));
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use common::utils::{run_pair, run_until, Test};
+
+use sched::scheduler::Scheduler;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use temper::temper::locks::spinlock::{Lock, McsLock, TasLock, TicketLock, TtasLock};
+use temper::temper::memory::core::MemoryModel;
+use temper::temper::system::core::System;
+
+// Two threads each increment a counter twice under the lock. Every increment reads a different
+// value, and the spins it took are reported after the values.
+fn counter<L: Lock + 'static>(model: MemoryModel, lock: L) -> (Vec<usize>, usize) {
+    let s = System::new(model);
+
+    let test = Test::default();
+    let lock = Arc::new(lock);
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        let lock = lock.clone();
+        move || {
+            for i in 0..2 {
+                lock.lock();
+                let v = *test.a.get();
+                test.a.set(v + 1);
+                lock.unlock();
+                test.report_result(index * 2 + i, v);
+            }
+        }
+    });
+
+    let mut tr = test.results.lock().unwrap().clone();
+    tr.sort();
+    (tr, lock.spins().values().sum())
+}
+
+fn check<L: Lock + 'static, F: Fn() -> L>(lock: F) {
+    for model in [MemoryModel::Intel, MemoryModel::ARMv8, MemoryModel::POWER] {
+        assert!(run_until(
+            || counter(model, lock()).0,
+            vec![vec![0, 1, 2, 3]]
+        ));
+
+        // Sooner or later one thread has to wait for the other
+        assert!((0..1000).any(|_| counter(model, lock()).1 > 0));
+    }
+}
+
+#[test]
+fn test_tas() {
+    check(TasLock::default);
+}
+
+#[test]
+fn test_ttas() {
+    check(TtasLock::default);
+}
+
+#[test]
+fn test_ticket() {
+    check(TicketLock::default);
+}
+
+#[test]
+fn test_mcs() {
+    check(|| McsLock::new(2));
+}
+
+// Favours the first thread, giving the second a step only once every `period` choices
+struct Biased {
+    period: usize,
+    steps: usize,
+}
+
+impl Scheduler for Biased {
+    fn choose(&mut self, runnable: &[usize]) -> usize {
+        self.steps += 1;
+
+        let first = *runnable.iter().min().unwrap();
+        let second = *runnable.iter().max().unwrap();
+        let thread = if self.steps.is_multiple_of(self.period) {
+            second
+        } else {
+            first
+        };
+
+        runnable.iter().position(|&t| t == thread).unwrap()
+    }
+}
+
+// Each thread takes the lock `rounds` times, under a schedule biased towards the first. Returns
+// which thread took it each time, and the spins, by thread. Threads are numbered from 1.
+fn biased<L: Lock + 'static>(lock: L, rounds: usize) -> (Vec<usize>, HashMap<usize, usize>) {
+    let s = System::with_scheduler(
+        MemoryModel::Intel,
+        Biased {
+            period: 16,
+            steps: 0,
+        },
+    );
+
+    let lock = Arc::new(lock);
+    let order = Arc::new(Mutex::new(vec![]));
+
+    run_pair(s, |index| {
+        let (lock, order) = (lock.clone(), order.clone());
+        move || {
+            for _ in 0..rounds {
+                lock.lock();
+                order.lock().unwrap().push(index);
+                lock.unlock();
+            }
+        }
+    });
+
+    let order = order.lock().unwrap().clone();
+    (order, lock.spins())
+}
+
+#[test]
+fn test_starvation() {
+    // The favoured thread takes a TAS lock whenever it's free, so it never waits, and the other
+    // thread only gets the lock once the first is done with it
+    let (order, spins) = biased(TasLock::default(), 10);
+    assert_eq!(order, [[0; 10], [1; 10]].concat());
+    assert_eq!(spins.get(&1), None);
+
+    // A ticket lock serves the other thread as soon as it has a ticket, and the favoured thread
+    // waits its turn
+    let (order, spins) = biased(TicketLock::default(), 10);
+    assert!(order[..10].contains(&1));
+    assert!(spins[&1] > 0);
+}
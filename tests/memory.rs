@@ -2,10 +2,16 @@
 
 mod common;
 
+use common::litmus::{message_passing, Ordering};
 use common::utils::{run_until, Test};
 
-use temper::temper::memory::core::{set_model, Atomic, MemoryModel};
+use sched::scheduler::PctScheduler;
+
+use std::sync::{Arc, Mutex};
+use temper::temper::memory::address::LINE_SIZE;
+use temper::temper::memory::core::{Atomic, MemoryModel, PendingResult, SharedMemory};
 use temper::temper::system::core::System;
+use temper::temper::system::diff::ModelDiff;
 
 /* From Intel's memory model documentation
 
@@ -22,8 +28,7 @@ If a memfence is present, (0,0) is not a valid result
 */
 
 fn test_a(memfence: bool) -> Vec<usize> {
-    set_model(MemoryModel::Intel);
-    let s = System::new();
+    let s = System::new(MemoryModel::Intel);
 
     let test = Test::default();
 
@@ -53,7 +58,7 @@ fn test_a(memfence: bool) -> Vec<usize> {
 
     let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(fa), Box::new(fb)];
 
-    s.run(fns);
+    s.run(fns).unwrap();
 
     let tr = test.results.lock().unwrap();
     (*tr).clone()
@@ -72,11 +77,10 @@ fn test_a_runner() {
     ));
 }
 
-fn test_queue(iters: usize, model: MemoryModel) -> Vec<usize> {
+// A single producer, single consumer queue. Without the fence, ARM can publish the new index
+// before the element is written.
+fn test_queue(system: System, iters: usize, fence: bool) -> Vec<usize> {
     //let start = Utc::now();
-    set_model(model);
-    let system = System::new();
-
     let test = Test::default();
 
     let fa = {
@@ -86,8 +90,7 @@ fn test_queue(iters: usize, model: MemoryModel) -> Vec<usize> {
                 let i = *test.a.get();
                 test.arr.set(i, x);
 
-                // ARM requires fence here
-                if model == MemoryModel::ARM {
+                if fence {
                     Atomic::<()>::fence();
                 }
 
@@ -119,7 +122,7 @@ fn test_queue(iters: usize, model: MemoryModel) -> Vec<usize> {
 
     let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(fa), Box::new(fb)];
 
-    system.run(fns);
+    system.run(fns).unwrap();
 
     //println!("Elapsed {}", (Utc::now() - start));
 
@@ -132,11 +135,247 @@ fn test_queue_runner() {
     let size = 20;
     let expected = (0..size).sum();
     assert!(run_until(
-        || test_queue(size, MemoryModel::ARM),
+        || test_queue(System::new(MemoryModel::ARM), size, true),
         vec![vec![expected]]
     ));
     assert!(run_until(
-        || test_queue(size, MemoryModel::Intel),
+        || test_queue(System::new(MemoryModel::Intel), size, false),
         vec![vec![expected]]
     ));
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_queue_diff() {
+    let size = 5;
+    let expected = vec![(0..size).sum()];
+    let models = [MemoryModel::Intel, MemoryModel::ARM];
+
+    let diff = ModelDiff::models(&models, 0..300, 1, |s| test_queue(s, size, false));
+
+    // A row per result, with a count per model, or - where the model never had it
+    let table = diff.to_string();
+    let mut lines = table.lines();
+    let header: Vec<&str> = lines.next().unwrap().split_whitespace().collect();
+    assert_eq!(header, vec!["outcome", "Intel", "ARM"]);
+
+    let rows: Vec<Vec<&str>> = lines.map(|l| l.split_whitespace().collect()).collect();
+    assert_eq!(rows.len(), diff.results().len());
+    for row in &rows {
+        assert_eq!(row.len(), 3);
+        assert!(row[1..]
+            .iter()
+            .all(|c| *c == "-" || c.parse::<usize>().is_ok()));
+    }
+
+    // Intel always sums correctly
+    let row = rows
+        .iter()
+        .find(|r| r[0] == format!("{:?}", expected))
+        .unwrap();
+    assert_eq!(row[1], "300");
+
+    // Only ARM needs the fence
+    assert_eq!(
+        diff.outcomes("Intel")
+            .unwrap()
+            .results()
+            .collect::<Vec<_>>(),
+        vec![&expected]
+    );
+    assert!(diff.differences().iter().all(|r| **r != expected));
+    assert!(!diff.differences().is_empty());
+
+    let fenced = ModelDiff::models(&models, 0..100, 1, |s| test_queue(s, size, true));
+    assert!(fenced.differences().is_empty());
+    assert_eq!(
+        fenced.results().into_iter().collect::<Vec<_>>(),
+        vec![&expected]
+    );
+}
+
+// Two threads incrementing with a separate get and set. Both reading zero needs one preemption.
+fn test_lost_update(system: System) -> Vec<usize> {
+    let test = Test::default();
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = (0..2)
+        .map(|i| {
+            let test = test.clone();
+            Box::new(move || {
+                let v = *test.a.get();
+                test.a.set(v + 1);
+                test.report_result(i, v);
+            }) as Box<dyn FnMut() + Send>
+        })
+        .collect();
+
+    system.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_lost_update_pct() {
+    assert!((0..200).any(|_| {
+        let system = System::with_scheduler(MemoryModel::Intel, PctScheduler::with_depth(2, 6));
+        test_lost_update(system) == vec![0, 0]
+    }));
+}
+
+/* Store buffering with forwarding, under x86-TSO
+
+Thread 1:
+a = 1
+print(a)
+print(b)
+
+Thread 2:
+b = 1
+print(b)
+print(a)
+
+Each thread always sees its own store, forwarded from its store buffer, while the other thread
+may not. A fence drains the buffer, ruling out both threads missing the other's store.
+*/
+
+fn test_tso_forwarding(memfence: bool) -> Vec<usize> {
+    let s = System::new(MemoryModel::TSO);
+
+    let test = Test::default();
+
+    let thread = |mine: Arc<Atomic<usize>>, theirs: Arc<Atomic<usize>>, index: usize| {
+        let test = test.clone();
+        move || {
+            mine.set(1);
+            let own = *mine.get();
+            if memfence {
+                Atomic::<()>::fence()
+            }
+            let other = *theirs.get();
+            test.report_result(index * 2, own);
+            test.report_result(index * 2 + 1, other);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new(thread(test.a.clone(), test.b.clone(), 0)),
+        Box::new(thread(test.b.clone(), test.a.clone(), 1)),
+    ];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_tso_forwarding_runner() {
+    assert!(run_until(
+        || test_tso_forwarding(false),
+        vec![
+            vec![1, 0, 1, 0],
+            vec![1, 0, 1, 1],
+            vec![1, 1, 1, 0],
+            vec![1, 1, 1, 1]
+        ],
+    ));
+
+    assert!(run_until(
+        || test_tso_forwarding(true),
+        vec![vec![1, 0, 1, 1], vec![1, 1, 1, 0], vec![1, 1, 1, 1]],
+    ));
+}
+
+#[derive(Clone, Default, PartialEq, Debug)]
+struct Record {
+    name: String,
+    count: usize,
+}
+
+// Records are read and written whole, so the reader sees either the default or the written one
+fn publish_record(model: MemoryModel) -> (String, usize) {
+    let s = System::new(model);
+
+    let records = Arc::new(SharedMemory::<Record>::new(2));
+    let seen = Arc::new(Mutex::new(Record::default()));
+
+    let writer = {
+        let records = records.clone();
+        move || {
+            records.set(
+                0,
+                Record {
+                    name: "temper".to_string(),
+                    count: 1,
+                },
+            );
+        }
+    };
+
+    let reader = {
+        let records = records.clone();
+        let seen = seen.clone();
+        move || {
+            *seen.lock().unwrap() = records.get(0).wait();
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(writer), Box::new(reader)];
+
+    s.run(fns).unwrap();
+
+    let seen = seen.lock().unwrap();
+    (seen.name.clone(), seen.count)
+}
+
+#[test]
+fn test_records() {
+    for model in [MemoryModel::Intel, MemoryModel::TSO, MemoryModel::POWER] {
+        assert!(run_until(
+            || publish_record(model),
+            vec![("".to_string(), 0), ("temper".to_string(), 1)]
+        ));
+    }
+}
+
+#[test]
+fn test_pending_result_is_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<PendingResult<Record>>();
+}
+
+#[test]
+fn test_addresses() {
+    let byte = Atomic::new(0u8);
+    let word = Atomic::new(0u64);
+    assert_eq!((byte.size(), word.size()), (1, 8));
+    assert_eq!(word.address() % 8, 0);
+    assert!(word.address() > byte.address());
+    assert!(word.address() >= byte.address() + byte.size());
+
+    let arr = SharedMemory::<u64>::new(16);
+    assert_eq!(arr.address(0) % LINE_SIZE, 0);
+    assert_eq!(arr.size(0), 8);
+    assert_eq!(arr.address(1), arr.address(0) + arr.size(0));
+    assert_eq!(arr.address(7) / LINE_SIZE, arr.address(0) / LINE_SIZE);
+    assert_eq!(arr.address(8) / LINE_SIZE, arr.address(0) / LINE_SIZE + 1);
+}
+
+// Each system has a model of its own, so systems with different models can share a thread
+#[test]
+fn test_model_per_system() {
+    let mut intel = vec![];
+
+    assert!(run_until(
+        || {
+            intel.push(message_passing(
+                System::new(MemoryModel::Intel),
+                Ordering::Relaxed,
+            ));
+            message_passing(System::new(MemoryModel::ARM), Ordering::Relaxed)
+        },
+        vec![vec![0, 0], vec![0, 1], vec![1, 0], vec![1, 1]]
+    ));
+
+    assert!(!intel.contains(&vec![1, 0]));
+}
@@ -0,0 +1,190 @@
+mod common;
+
+use common::utils::{run_until, Test};
+
+use std::any::Any;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use temper::temper::memory::barrier::Access;
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::{submit, Category, Op, Resource, System};
+use temper::temper::system::result::{channel, PendingResult};
+
+// A disk outside the memory model. Each thread's disk operations execute in order, but nothing
+// orders them against its memory operations apart from fences.
+#[derive(Clone, Default)]
+struct Disk {
+    blocks: Arc<Mutex<HashMap<usize, usize>>>,
+}
+
+struct DiskOp {
+    block: usize,
+    access: Access,
+    func: Box<dyn Fn() + Send>,
+    abandon: Box<dyn Fn() + Send>,
+}
+
+impl Op for DiskOp {
+    fn blocks(&self, _other: &(dyn Op + Send)) -> bool {
+        true
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn execute(&self) {
+        (self.func)()
+    }
+
+    fn abandon(&self) {
+        (self.abandon)()
+    }
+
+    fn category(&self) -> Category {
+        Category::Io
+    }
+
+    fn resources(&self) -> Vec<Resource> {
+        vec![Resource::new("disk", self.block, self.access)]
+    }
+
+    fn name(&self) -> String {
+        format!("Disk{:?} {}", self.access, self.block)
+    }
+}
+
+impl Disk {
+    fn write(&self, block: usize, value: usize) {
+        let blocks = self.blocks.clone();
+
+        submit(DiskOp {
+            block,
+            access: Access::Write,
+            func: Box::new(move || {
+                blocks.lock().unwrap().insert(block, value);
+            }),
+            abandon: Box::new(|| {}),
+        });
+    }
+
+    fn read(&self, block: usize) -> PendingResult<usize> {
+        let blocks = self.blocks.clone();
+        let (completion, result) = channel();
+        let waiter = completion.waiter();
+
+        submit(DiskOp {
+            block,
+            access: Access::Read,
+            func: Box::new(move || {
+                completion.complete(blocks.lock().unwrap().get(&block).copied().unwrap_or(0))
+            }),
+            abandon: Box::new(move || waiter.signal()),
+        });
+
+        result
+    }
+}
+
+/* Writing a block, then flagging it
+
+Thread 1:
+disk[0] = 1
+fence (optional)
+flag = 1
+
+Thread 2:
+if flag == 1:
+  print(disk[0])
+*/
+
+fn disk_flag(fence: bool) -> Vec<usize> {
+    let (disk, test) = (Disk::default(), Test::default());
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new({
+            let (disk, test) = (disk.clone(), test.clone());
+            move || {
+                disk.write(0, 1);
+                if fence {
+                    Atomic::<usize>::fence();
+                }
+                test.a.set(1);
+            }
+        }),
+        Box::new({
+            let test = test.clone();
+            move || {
+                if *test.a.get() == 1 {
+                    test.report_result(0, 10 + *disk.read(0));
+                }
+            }
+        }),
+    ];
+
+    System::new(MemoryModel::Intel).run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_disk_fence() {
+    // Without the fence the flag can be set before the block is written
+    assert!(run_until(
+        || disk_flag(false),
+        vec![vec![], vec![10], vec![11]]
+    ));
+    assert!(run_until(|| disk_flag(true), vec![vec![], vec![11]]));
+}
+
+#[test]
+fn test_disk_resources() {
+    // Reads wait on the writes the thread queued before them, and hand their results back
+    let disk = Disk::default();
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new({
+        let disk = disk.clone();
+        move || {
+            disk.write(0, 1);
+            disk.write(1, 2);
+            assert_eq!(*disk.read(0), 1);
+            assert_eq!(*disk.read(1), 2);
+        }
+    })];
+
+    System::new(MemoryModel::ARMv8).run(fns).unwrap();
+}
+
+#[test]
+fn test_log() {
+    let (disk, flag) = (Disk::default(), Arc::new(Atomic::new(0usize)));
+    let address = flag.address();
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(move || {
+        disk.write(3, 1);
+        Atomic::<usize>::fence();
+        flag.set(1);
+    })];
+
+    let log = System::new(MemoryModel::Intel)
+        .with_log()
+        .run(fns)
+        .unwrap()
+        .log
+        .unwrap();
+
+    let entries: Vec<(usize, Category, String)> = log
+        .into_iter()
+        .map(|e| (e.thread, e.category, e.name))
+        .collect();
+
+    assert_eq!(
+        entries,
+        vec![
+            (1, Category::Io, "DiskWrite 3".to_string()),
+            (1, Category::Fence, "Fence".to_string()),
+            (1, Category::Memory, format!("Set {:#x}", address)),
+        ]
+    );
+}
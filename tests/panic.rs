@@ -0,0 +1,74 @@
+use sched::scheduler::Scheduler;
+use std::sync::Arc;
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::{System, SCHEDULER};
+
+#[test]
+fn test_panic_tears_down() {
+    let flag = Arc::new(Atomic::new(0));
+
+    // The second thread waits for a flag the first never sets, so it only stops by being torn down
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new({
+            let flag = flag.clone();
+            move || {
+                let v = *flag.get();
+                assert_eq!(v, 1, "flag not set");
+            }
+        }),
+        Box::new(move || while *flag.get() == 0 {}),
+    ];
+
+    let panic = System::with_seed(MemoryModel::Intel, 42)
+        .run(fns)
+        .unwrap_err();
+
+    assert_eq!(panic.thread, 1);
+    assert_eq!(panic.seed, Some(42));
+    assert!(panic.message().unwrap().contains("flag not set"));
+    assert!(panic.to_string().ends_with("(seed 42)"));
+}
+
+#[test]
+fn test_no_panic() {
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(|| {}), Box::new(|| {})];
+    assert!(System::new(MemoryModel::Intel).run(fns).is_ok());
+}
+
+// Gives up partway through the run, as a replay does when the program stops matching its trace
+struct Diverging {
+    steps: usize,
+}
+
+impl Scheduler for Diverging {
+    fn choose(&mut self, _runnable: &[usize]) -> usize {
+        assert!(self.steps > 0, "diverged");
+        self.steps -= 1;
+        0
+    }
+}
+
+#[test]
+fn test_scheduler_panic() {
+    for coroutines in [false, true] {
+        let flag = Arc::new(Atomic::new(0));
+
+        // Both threads are parked on results when the scheduler gives up
+        let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+            Box::new({
+                let flag = flag.clone();
+                move || while *flag.get() == 0 {}
+            }),
+            Box::new(move || while *flag.get() == 0 {}),
+        ];
+
+        let mut system = System::with_scheduler(MemoryModel::Intel, Diverging { steps: 5 });
+        if coroutines {
+            system = system.with_coroutines();
+        }
+
+        let panic = system.run(fns).unwrap_err();
+        assert_eq!(panic.thread, SCHEDULER);
+        assert_eq!(panic.to_string(), "scheduler panicked: diverged");
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use common::utils::run_pair;
+
+use std::sync::{Arc, Mutex};
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+use temper::temper::system::parallel::ParallelRunner;
+
+// Both threads try to claim a location with a weak compare exchange, read the other one, and
+// count themselves in. Under LL/SC the claims can fail spuriously, or because the other thread
+// wrote to the same cache line, so outcomes depend on the layout of memory as well as the schedule.
+fn claims(s: System) -> Vec<usize> {
+    let locations = Arc::new([
+        Atomic::new(0usize),
+        Atomic::new(0usize),
+        Atomic::new(0usize),
+    ]);
+    let results = Arc::new(Mutex::new(vec![0; 2]));
+
+    run_pair(s.with_coroutines(), |index| {
+        let locations = locations.clone();
+        let results = results.clone();
+        move || {
+            let claimed = locations[index].compare_exchange_weak(0, index + 1).is_ok();
+            let seen = *locations[1 - index].get();
+            let turn = *locations[2].fetch_add(1);
+            results.lock().unwrap()[index] = turn * 100 + claimed as usize * 10 + seen;
+        }
+    });
+
+    let res = results.lock().unwrap().clone();
+    res
+}
+
+#[test]
+fn test_worker_count() {
+    let single = ParallelRunner::new(MemoryModel::ARMv8, 1).run(0..300, claims);
+    assert_eq!(single.runs(), 300);
+    assert!(single.outcomes.len() > 1);
+
+    for workers in [2, 3, 8] {
+        assert_eq!(
+            ParallelRunner::new(MemoryModel::ARMv8, workers).run(0..300, claims),
+            single
+        );
+    }
+
+    // Each outcome's seed reproduces it
+    for (result, outcome) in single.outcomes.iter() {
+        assert_eq!(
+            &ParallelRunner::new(MemoryModel::ARMv8, 1)
+                .run(outcome.seed..outcome.seed + 1, claims)
+                .results()
+                .next()
+                .unwrap(),
+            &result
+        );
+    }
+}
+
+#[test]
+fn test_intel_claims() {
+    // On Intel a weak compare exchange is the locked strong one, so both claims succeed
+    let outcomes = ParallelRunner::new(MemoryModel::Intel, 4).run(0..100, claims);
+    assert!(outcomes.results().all(|r| r.iter().all(|v| v % 100 >= 10)));
+}
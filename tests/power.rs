@@ -0,0 +1,219 @@
+mod common;
+
+use common::utils::{run_until, Test};
+
+use temper::temper::memory::barrier::Barrier;
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+use temper::temper::system::parallel::ParallelRunner;
+
+fn all_outcomes(len: usize) -> Vec<Vec<usize>> {
+    (0..1 << len)
+        .map(|n| (0..len).rev().map(|bit| (n >> bit) & 1).collect())
+        .collect()
+}
+
+/* Independent reads of independent writes
+
+Thread 1:
+x = 1
+
+Thread 2:
+y = 1
+
+Thread 3:
+print(x)
+sync
+print(y)
+
+Thread 4:
+print(y)
+sync
+print(x)
+
+(1, 0, 1, 0) means the readers saw the two writes in opposite orders. Only a full sync rules it
+out on POWER. An lwsync doesn't, because the writes can reach the readers in any order.
+*/
+
+fn iriw(sync: bool) -> Vec<usize> {
+    let s = System::new(MemoryModel::POWER);
+
+    let test = Test::default();
+
+    let writer = |target: usize| {
+        let test = test.clone();
+        move || {
+            let target = if target == 0 { &test.a } else { &test.b };
+            target.set(1);
+        }
+    };
+
+    let reader = |index: usize| {
+        let test = test.clone();
+        move || {
+            let (first, second) = if index == 0 {
+                (&test.a, &test.b)
+            } else {
+                (&test.b, &test.a)
+            };
+            let r1 = first.get();
+            if sync {
+                Atomic::<()>::fence()
+            } else {
+                Atomic::<()>::lwsync()
+            }
+            let r2 = second.get();
+            test.report_result(index * 2, *r1);
+            test.report_result(index * 2 + 1, *r2);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new(writer(0)),
+        Box::new(writer(1)),
+        Box::new(reader(0)),
+        Box::new(reader(1)),
+    ];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_iriw() {
+    assert!(run_until(|| iriw(false), all_outcomes(4)));
+
+    let mut outcomes = all_outcomes(4);
+    outcomes.retain(|o| *o != vec![1, 0, 1, 0]);
+    assert!(run_until(|| iriw(true), outcomes));
+}
+
+/* Write to read causality
+
+Thread 1:
+x = 1
+
+Thread 2:
+print(x)
+y = 1
+
+Thread 3:
+print(y)
+lwsync
+print(x)
+
+The second thread stores only after it has seen x, but on POWER y can still reach the third thread
+before x does. An lwsync in the second thread is cumulative: x must reach every thread before y.
+A multicopy atomic model like ARMv8 never allows (1, 1, 0) here.
+*/
+
+fn wrc(s: System, model: MemoryModel, cumulative: bool) -> Vec<usize> {
+    let test = Test::default();
+
+    let barrier = move || match model {
+        MemoryModel::POWER => Atomic::<()>::lwsync(),
+        _ => Atomic::<()>::barrier(Barrier::DMB_ISHLD),
+    };
+
+    let t1 = {
+        let test = test.clone();
+        move || {
+            test.a.set(1);
+        }
+    };
+
+    let t2 = {
+        let test = test.clone();
+        move || {
+            let r1 = *test.a.get();
+            if cumulative {
+                barrier()
+            }
+            test.b.set(1);
+            test.report_result(0, r1);
+        }
+    };
+
+    let t3 = {
+        let test = test.clone();
+        move || {
+            let r2 = test.b.get();
+            barrier();
+            let r3 = test.a.get();
+            test.report_result(1, *r2);
+            test.report_result(2, *r3);
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(t1), Box::new(t2), Box::new(t3)];
+
+    s.run(fns).unwrap();
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_wrc() {
+    let mut causal = all_outcomes(3);
+    causal.retain(|o| *o != vec![1, 1, 0]);
+
+    // (1, 1, 0) is rare enough that random runs can miss it, so the weak case runs a fixed seed
+    // range known to reach every outcome
+    let weak = ParallelRunner::new(MemoryModel::POWER, 4)
+        .run(0..11_000, |s| wrc(s, MemoryModel::POWER, false));
+    assert_eq!(weak.results().cloned().collect::<Vec<_>>(), all_outcomes(3));
+
+    let random = |model, cumulative| move || wrc(System::new(model), model, cumulative);
+    assert!(run_until(random(MemoryModel::POWER, true), causal.clone()));
+    assert!(run_until(random(MemoryModel::ARMv8, false), causal));
+}
+
+/* Store then load, across a barrier
+
+x = 1
+lwsync or sync
+print(y)
+
+Only a sync keeps the load from executing before the store.
+*/
+
+fn store_load(sync: bool) -> Vec<String> {
+    let test = Test::default();
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(move || {
+        test.a.set(1);
+        if sync {
+            Atomic::<()>::fence()
+        } else {
+            Atomic::<()>::lwsync()
+        }
+        test.b.get();
+    })];
+
+    let log = System::new(MemoryModel::POWER)
+        .with_log()
+        .run(fns)
+        .unwrap()
+        .log
+        .unwrap();
+
+    log.into_iter()
+        .filter_map(|e| e.name.split(' ').next().map(str::to_string))
+        .filter(|op| op == "Set" || op == "Get")
+        .collect()
+}
+
+#[test]
+fn test_lwsync_store_load() {
+    let ordered = vec!["Set".to_string(), "Get".to_string()];
+    let reordered = vec!["Get".to_string(), "Set".to_string()];
+
+    assert!(run_until(
+        || store_load(false),
+        vec![ordered.clone(), reordered]
+    ));
+    assert!(run_until(|| store_load(true), vec![ordered]));
+}
@@ -0,0 +1,131 @@
+mod common;
+
+use common::utils::{run_pair, run_until, Test};
+
+use temper::temper::memory::core::MemoryModel;
+use temper::temper::system::core::System;
+
+// Two threads incrementing twice each never lose an update, so every fetch_add sees a different value
+fn increments(model: MemoryModel) -> Vec<usize> {
+    let s = System::new(model);
+
+    let test = Test::default();
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        move || {
+            let first = *test.a.fetch_add(1);
+            let second = *test.a.fetch_add(1);
+            test.report_result(index * 2, first);
+            test.report_result(index * 2 + 1, second);
+        }
+    });
+
+    let mut tr = test.results.lock().unwrap().clone();
+    tr.sort();
+    tr
+}
+
+#[test]
+fn test_fetch_add() {
+    for model in MemoryModel::ALL {
+        assert!(run_until(|| increments(model), vec![vec![0, 1, 2, 3]]));
+    }
+}
+
+// Both threads try to claim the location. Exactly one succeeds, and the other sees its value.
+fn claim(model: MemoryModel, weak: bool) -> Vec<usize> {
+    let s = System::new(model);
+
+    let test = Test::default();
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        move || {
+            let res = if weak {
+                *test.a.compare_exchange_weak(0, index + 1)
+            } else {
+                *test.a.compare_exchange(0, index + 1)
+            };
+            test.report_result(index, res.unwrap_or_else(|v| v + 10));
+        }
+    });
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_compare_exchange() {
+    for model in MemoryModel::ALL {
+        assert!(run_until(
+            || claim(model, false),
+            vec![vec![0, 11], vec![12, 0]]
+        ));
+    }
+
+    // A weak compare exchange on Intel is the strong one
+    assert!(run_until(
+        || claim(MemoryModel::Intel, true),
+        vec![vec![0, 11], vec![12, 0]]
+    ));
+
+    // With LL/SC it can fail without either thread writing
+    assert!(run_until(
+        || claim(MemoryModel::ARMv8, true),
+        vec![
+            vec![0, 11],
+            vec![12, 0],
+            vec![0, 10],
+            vec![10, 0],
+            vec![10, 10]
+        ]
+    ));
+}
+
+/* Store buffering with swaps
+
+Thread 1:
+swap(a, 1)
+print(b)
+
+Thread 2:
+swap(b, 1)
+print(a)
+
+A LOCK prefixed instruction is a full barrier on Intel, ruling out (0, 0). Elsewhere the LL/SC
+loop completes before the thread carries on, which is stronger than the hardware.
+*/
+
+fn swap_buffering(model: MemoryModel) -> Vec<usize> {
+    let s = System::new(model);
+
+    let test = Test::default();
+
+    run_pair(s, |index| {
+        let test = test.clone();
+        move || {
+            let (mine, theirs) = if index == 0 {
+                (&test.a, &test.b)
+            } else {
+                (&test.b, &test.a)
+            };
+            mine.swap(1);
+            let res = *theirs.get();
+            test.report_result(index, res);
+        }
+    });
+
+    let tr = test.results.lock().unwrap();
+    (*tr).clone()
+}
+
+#[test]
+fn test_swap_ordering() {
+    for model in [MemoryModel::Intel, MemoryModel::TSO] {
+        assert!(run_until(
+            || swap_buffering(model),
+            vec![vec![0, 1], vec![1, 0], vec![1, 1]]
+        ));
+    }
+}
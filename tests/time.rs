@@ -0,0 +1,141 @@
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use temper::temper::memory::core::{Atomic, MemoryModel};
+use temper::temper::system::core::System;
+use temper::temper::system::time::{sleep, timeout, timer, Instant};
+
+const SECOND: Duration = Duration::from_secs(1);
+
+#[test]
+fn test_sleep() {
+    let woke = Arc::new(Mutex::new(vec![]));
+
+    let sleeper = |secs: u64| {
+        let woke = woke.clone();
+        move || {
+            sleep(SECOND * secs as u32);
+            woke.lock().unwrap().push(Instant::now().since_start());
+        }
+    };
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(sleeper(5)), Box::new(sleeper(3))];
+
+    let started = std::time::Instant::now();
+    let report = System::new(MemoryModel::Intel).run(fns).unwrap();
+
+    // The sleeps overlap, and take no real time
+    assert_eq!(report.time, SECOND * 5);
+    assert_eq!(*woke.lock().unwrap(), vec![SECOND * 3, SECOND * 5]);
+    assert!(started.elapsed() < SECOND);
+}
+
+#[test]
+fn test_timer() {
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![Box::new(|| {
+        let flag = Atomic::new(0);
+
+        // The thread carries on while the timer runs
+        let fired = timer(SECOND);
+        flag.set(1);
+        assert_eq!(*flag.get(), 1);
+        assert_eq!(Instant::now().since_start(), Duration::ZERO);
+
+        assert_eq!(fired.since_start(), SECOND);
+        assert_eq!(Instant::now().since_start(), SECOND);
+    })];
+
+    System::new(MemoryModel::ARMv8).run(fns).unwrap();
+}
+
+/* Retrying with exponential backoff
+
+Thread 1:
+sleep(1s)
+flag = 1
+
+Thread 2:
+backoff = 10ms
+while flag == 0:
+  sleep(backoff)
+  backoff *= 2
+*/
+
+fn backoff(s: System) -> (usize, Duration) {
+    let flag = Arc::new(Atomic::new(0));
+    let result = Arc::new(Mutex::new((0, Duration::ZERO)));
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new({
+            let flag = flag.clone();
+            move || {
+                sleep(SECOND);
+                flag.set(1);
+            }
+        }),
+        Box::new({
+            let result = result.clone();
+            move || {
+                let (start, mut delay, mut attempts) =
+                    (Instant::now(), Duration::from_millis(10), 1);
+
+                while *flag.get() == 0 {
+                    sleep(delay);
+                    delay *= 2;
+                    attempts += 1;
+                }
+
+                *result.lock().unwrap() = (attempts, start.elapsed());
+            }
+        }),
+    ];
+
+    s.run(fns).unwrap();
+
+    let r = *result.lock().unwrap();
+    r
+}
+
+#[test]
+fn test_backoff() {
+    // Retries at 10, 30, 70, 150, 310, 630 and 1270ms, whatever the schedule
+    for seed in 0..20 {
+        let expected = (8, Duration::from_millis(1270));
+
+        assert_eq!(
+            backoff(System::with_seed(MemoryModel::ARMv8, seed)),
+            expected
+        );
+        assert_eq!(
+            backoff(System::with_seed(MemoryModel::ARMv8, seed).with_coroutines()),
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_timeout() {
+    let flag = Arc::new(Atomic::new(0));
+
+    let fns: Vec<Box<dyn FnMut() + Send>> = vec![
+        Box::new({
+            let flag = flag.clone();
+            move || {
+                sleep(SECOND * 2);
+                flag.set(1);
+            }
+        }),
+        Box::new(move || {
+            let poll = || (*flag.get() == 1).then_some(());
+
+            assert_eq!(timeout(SECOND, poll), None);
+            assert_eq!(Instant::now().since_start(), SECOND);
+
+            assert_eq!(timeout(SECOND * 5, poll), Some(()));
+            let now = Instant::now().since_start();
+            assert!(now >= SECOND * 2 && now <= SECOND * 2 + Duration::from_millis(1));
+        }),
+    ];
+
+    let report = System::new(MemoryModel::TSO).run(fns).unwrap();
+    assert!(report.time < SECOND * 3);
+}